@@ -0,0 +1,98 @@
+use super::Cfg;
+use crate::bb::BasicBlockInner;
+use crate::jump::{ForeachTarget, Unconditional};
+use crate::{Arena, BbId};
+use alloc::collections::BTreeMap as Map;
+use alloc::vec::Vec;
+
+/// The call graph of an [`Arena`], derived from its
+/// [`Unconditional::Call`] terminators.
+///
+/// Every public BB and every callee is the entry of a function, which
+/// consists of all BBs reachable from it inside of its [`Cfg`]
+/// (a BB may belong to multiple functions). Functions are identified
+/// by their entry BB; extern placeholders are functions without calls.
+#[derive(Clone, Debug, Default)]
+pub struct CallGraph {
+    callees: Map<BbId, Vec<BbId>>,
+    callers: Map<BbId, Vec<BbId>>,
+    call_sites: Map<BbId, Vec<BbId>>,
+}
+
+impl CallGraph {
+    pub fn new<S, C>(arena: &Arena<S, C>) -> Self
+    where
+        S: ForeachTarget<JumpTarget = BbId>,
+        C: ForeachTarget<JumpTarget = BbId>,
+    {
+        let bbs = arena.bbs();
+        let mut ret = Self::default();
+        let mut todo: Vec<BbId> = bbs
+            .iter()
+            .filter(|(_, bb)| bb.is_public)
+            .map(|(&n, _)| n)
+            .collect();
+        while let Some(f) = todo.pop() {
+            if ret.callees.contains_key(&f) {
+                continue;
+            }
+            let cfg = Cfg::new(arena, Some(f));
+            let mut callees = Vec::new();
+            let mut call_sites = Vec::new();
+            for &n in cfg.rpo() {
+                if let BasicBlockInner::Concrete {
+                    next: Unconditional::Call(c),
+                    ..
+                } = &bbs[&n].inner
+                {
+                    if bbs.contains_key(&c.callee) {
+                        call_sites.push(n);
+                        callees.push(c.callee);
+                        todo.push(c.callee);
+                    }
+                }
+            }
+            callees.sort_unstable();
+            callees.dedup();
+            call_sites.sort_unstable();
+            for &i in callees.iter() {
+                ret.callers.entry(i).or_default().push(f);
+            }
+            ret.callees.insert(f, callees);
+            ret.call_sites.insert(f, call_sites);
+        }
+        for i in ret.callers.values_mut() {
+            i.sort_unstable();
+        }
+        ret
+    }
+
+    /// All functions (entry BBs), sorted by id.
+    #[inline]
+    pub fn functions(&self) -> impl Iterator<Item = BbId> + '_ {
+        self.callees.keys().copied()
+    }
+
+    #[inline]
+    pub fn contains(&self, func: BbId) -> bool {
+        self.callees.contains_key(&func)
+    }
+
+    /// Returns the (deduplicated) functions called by the given function, sorted by id.
+    #[inline]
+    pub fn callees(&self, func: BbId) -> &[BbId] {
+        self.callees.get(&func).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Returns the (deduplicated) functions which call the given function, sorted by id.
+    #[inline]
+    pub fn callers(&self, func: BbId) -> &[BbId] {
+        self.callers.get(&func).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Returns the BBs of the given function which end with a call, sorted by id.
+    #[inline]
+    pub fn call_sites(&self, func: BbId) -> &[BbId] {
+        self.call_sites.get(&func).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
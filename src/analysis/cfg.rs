@@ -0,0 +1,255 @@
+use crate::bb::BasicBlockInner;
+use crate::id::Idx;
+use crate::jump::{ForeachTarget, Unconditional};
+use crate::{Arena, BbId};
+use alloc::collections::{BTreeMap as Map, BTreeSet};
+use alloc::vec::Vec;
+
+/// A snapshot of the control flow graph of an [`Arena`].
+///
+/// Every jump target of a BB (including targets inside of statements
+/// and `condjmp`) is treated as a successor edge, except for the callee
+/// of an [`Unconditional::Call`] (see [`CallGraph`](super::CallGraph)).
+/// `Placeholder` BBs have no successors. A BB terminated by [`Unconditional::Unknown`] is
+/// conservatively assumed to be able to jump to any entry point,
+/// a BB terminated by `Indirect(None)` to any labeled BB.
+///
+/// BBs ending with `Halt`, `Return` or `Unknown`, and placeholders,
+/// are considered exits of the graph.
+///
+/// The unwind target (landing pad) of a BB is a successor, too. But unlike
+/// the other edges, it can be taken before any statement of the BB, thus
+/// dataflow analyses treat it separately (see [`Cfg::unwind`] and
+/// [`Analysis::transfer_unwind`](super::dataflow::Analysis::transfer_unwind)).
+#[derive(Clone, Debug)]
+pub struct Cfg<I = BbId> {
+    entries: Vec<I>,
+    rpo: Vec<I>,
+    succs: Map<I, Vec<I>>,
+    preds: Map<I, Vec<I>>,
+    exits: Vec<I>,
+    unwind: Map<I, I>,
+    // unwind edges whose target isn't reachable along any other edge
+    unwind_only: BTreeSet<(I, I)>,
+}
+
+impl<I> Default for Cfg<I> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            rpo: Vec::new(),
+            succs: Map::new(),
+            preds: Map::new(),
+            exits: Vec::new(),
+            unwind: Map::new(),
+            unwind_only: BTreeSet::new(),
+        }
+    }
+}
+
+/// Calculates the reverse postorder of all nodes reachable from `roots`.
+pub(crate) fn reverse_postorder<'a, I, R, F>(roots: R, succs: F) -> Vec<I>
+where
+    I: Idx + 'a,
+    R: IntoIterator<Item = I>,
+    F: Fn(I) -> &'a [I],
+{
+    let mut visited = BTreeSet::new();
+    let mut postorder = Vec::new();
+    let mut stack: Vec<(I, usize)> = Vec::new();
+    for root in roots {
+        if !visited.insert(root) {
+            continue;
+        }
+        stack.push((root, 0));
+        while let Some((n, pos)) = stack.last_mut() {
+            let n = *n;
+            if let Some(&s) = succs(n).get(*pos) {
+                *pos += 1;
+                if visited.insert(s) {
+                    stack.push((s, 0));
+                }
+            } else {
+                stack.pop();
+                postorder.push(n);
+            }
+        }
+    }
+    postorder.reverse();
+    postorder
+}
+
+fn push_unique<I: PartialEq>(v: &mut Vec<I>, x: I) {
+    if !v.contains(&x) {
+        v.push(x);
+    }
+}
+
+impl<I: Idx> Cfg<I> {
+    /// Builds the control flow graph of all BBs reachable from `entries`.
+    /// Entries which don't exist inside of the arena are ignored.
+    pub fn new<S, C, E>(arena: &Arena<S, C, I>, entries: E) -> Self
+    where
+        S: ForeachTarget<JumpTarget = I>,
+        C: ForeachTarget<JumpTarget = I>,
+        E: IntoIterator<Item = I>,
+    {
+        let bbs = arena.bbs();
+        let mut ret = Self::default();
+        for i in entries {
+            if bbs.contains_key(&i) {
+                push_unique(&mut ret.entries, i);
+            }
+        }
+
+        let mut todo = ret.entries.clone();
+        while let Some(n) = todo.pop() {
+            if ret.succs.contains_key(&n) {
+                continue;
+            }
+            let bb = &bbs[&n];
+            let mut targets = Vec::new();
+            if let BasicBlockInner::Concrete {
+                statements,
+                condjmp,
+                next,
+                unwind,
+            } = &bb.inner
+            {
+                statements.foreach_target(|&t| targets.push(t));
+                let unwind_pos = targets.len();
+                targets.extend(unwind.iter().copied());
+                condjmp.foreach_target(|&t| targets.push(t));
+                match next {
+                    // the callee is only reachable through the call graph
+                    Unconditional::Call(c) => targets.push(c.continuation),
+                    _ => next.foreach_target(|&t| targets.push(t)),
+                }
+                if let Some(&t) = unwind.as_ref().filter(|t| bbs.contains_key(t)) {
+                    ret.unwind.insert(n, t);
+                    let is_only = targets
+                        .iter()
+                        .enumerate()
+                        .all(|(i, &x)| i == unwind_pos || x != t);
+                    if is_only {
+                        ret.unwind_only.insert((n, t));
+                    }
+                }
+            }
+            let mut succs = Vec::new();
+            for t in targets {
+                if bbs.contains_key(&t) {
+                    push_unique(&mut succs, t);
+                }
+            }
+            let is_exit = match &bb.inner {
+                BasicBlockInner::Concrete { next, .. } => match next {
+                    Unconditional::Jump(_)
+                    | Unconditional::Switch(_)
+                    | Unconditional::Indirect(Some(_))
+                    | Unconditional::Call(_) => false,
+                    Unconditional::Indirect(None) => {
+                        for i in arena.labels().values() {
+                            if bbs.contains_key(i) {
+                                push_unique(&mut succs, *i);
+                            }
+                        }
+                        false
+                    }
+                    Unconditional::Unknown => {
+                        for &i in ret.entries.iter() {
+                            push_unique(&mut succs, i);
+                        }
+                        true
+                    }
+                    Unconditional::Halt | Unconditional::Return => true,
+                },
+                BasicBlockInner::Placeholder { .. } => true,
+            };
+            if is_exit {
+                ret.exits.push(n);
+            }
+            for &s in succs.iter() {
+                ret.preds.entry(s).or_default().push(n);
+                todo.push(s);
+            }
+            ret.succs.insert(n, succs);
+        }
+
+        let succs = &ret.succs;
+        ret.rpo = reverse_postorder(ret.entries.iter().copied(), |n| &succs[&n]);
+        ret.exits.sort_unstable();
+        for i in ret.preds.values_mut() {
+            i.sort_unstable();
+        }
+        ret
+    }
+
+    /// Builds the control flow graph using all public and all labeled BBs
+    /// as entry points.
+    pub fn from_arena<S, C>(arena: &Arena<S, C, I>) -> Self
+    where
+        S: ForeachTarget<JumpTarget = I>,
+        C: ForeachTarget<JumpTarget = I>,
+    {
+        let mut entries: Vec<_> = arena
+            .bbs()
+            .iter()
+            .filter(|(_, bb)| bb.is_public)
+            .map(|(&n, _)| n)
+            .chain(arena.labels().values().copied())
+            .collect();
+        entries.sort_unstable();
+        entries.dedup();
+        Self::new(arena, entries)
+    }
+
+    #[inline(always)]
+    pub fn entries(&self) -> &[I] {
+        &self.entries
+    }
+
+    /// All reachable BBs in reverse postorder.
+    #[inline(always)]
+    pub fn rpo(&self) -> &[I] {
+        &self.rpo
+    }
+
+    /// All reachable BBs which may leave the graph, sorted by id.
+    #[inline(always)]
+    pub fn exits(&self) -> &[I] {
+        &self.exits
+    }
+
+    #[inline]
+    pub fn contains(&self, bbid: I) -> bool {
+        self.succs.contains_key(&bbid)
+    }
+
+    /// Returns the (deduplicated) successors of the given BB,
+    /// in the order their jump targets appear inside of the BB.
+    #[inline]
+    pub fn succs(&self, bbid: I) -> &[I] {
+        self.succs.get(&bbid).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Returns the (deduplicated) reachable predecessors of the given BB, sorted by id.
+    #[inline]
+    pub fn preds(&self, bbid: I) -> &[I] {
+        self.preds.get(&bbid).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Returns the landing pad of the given BB, if it has one.
+    #[inline]
+    pub fn unwind(&self, bbid: I) -> Option<I> {
+        self.unwind.get(&bbid).copied()
+    }
+
+    /// Returns `true` if the edge `from -> to` only exists
+    /// because `to` is the landing pad of `from`.
+    #[inline]
+    pub fn is_unwind_edge(&self, from: I, to: I) -> bool {
+        self.unwind_only.contains(&(from, to))
+    }
+}
@@ -0,0 +1,272 @@
+//! Generic worklist-based dataflow solver.
+//!
+//! An analysis is described by implementing [`Analysis`] for some
+//! type, which provides the [`Lattice`] of facts, the direction,
+//! and the transfer function of every BB. Statement types can provide
+//! per-statement transfer functions by implementing [`Transfer`],
+//! which can be applied to a whole BB via [`transfer_statements`].
+//!
+//! Unwind edges (see [`Cfg::unwind`]) can be taken before any statement
+//! of their BB. Thus they don't carry the output fact of the BB, but the
+//! result of [`Analysis::transfer_unwind`] instead.
+
+use super::Cfg;
+use crate::bb::{BasicBlock, BasicBlockInner};
+use crate::{Arena, BbId};
+use alloc::collections::{BTreeMap as Map, BTreeSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// facts flow from the entry points along the edges
+    Forward,
+    /// facts flow from the exits against the edges
+    Backward,
+}
+
+/// A join-semilattice of dataflow facts.
+pub trait Lattice: Clone + PartialEq {
+    /// The least element, used as the initial value of all facts.
+    fn bottom() -> Self;
+
+    /// Joins `other` into `self`, returns `true` if `self` changed.
+    fn join(&mut self, other: &Self) -> bool;
+}
+
+/// Sets form a lattice under union.
+impl<T: Clone + Ord> Lattice for BTreeSet<T> {
+    #[inline]
+    fn bottom() -> Self {
+        BTreeSet::new()
+    }
+
+    fn join(&mut self, other: &Self) -> bool {
+        let old_len = self.len();
+        self.extend(other.iter().cloned());
+        self.len() != old_len
+    }
+}
+
+pub trait Analysis<S, C> {
+    type Fact: Lattice;
+
+    const DIRECTION: Direction;
+
+    /// The fact at the boundary of the CFG, which is the input of all entry
+    /// points (forward) or all exits (backward). Defaults to bottom.
+    #[inline]
+    fn boundary(&self, _bbid: BbId) -> Self::Fact {
+        Self::Fact::bottom()
+    }
+
+    /// Transforms the input fact of a BB into its output fact,
+    /// in the direction of the analysis.
+    fn transfer_bb(&self, bbid: BbId, bb: &BasicBlock<S, C, BbId>, fact: &mut Self::Fact);
+
+    /// Transforms the fact along the unwind edge of a BB, which can be taken
+    /// right before any of its statements. Forward, this transforms the
+    /// input fact of the BB into the fact passed to its landing pad.
+    /// Backward, this transforms the input fact of the landing pad into
+    /// the fact which gets joined into the input fact of the BB.
+    ///
+    /// Defaults to leaving the fact unchanged, which is exact for backward
+    /// gen/kill analyses (e.g. liveness). Forward analyses whose statements
+    /// change the fact should use [`transfer_unwind_statements`].
+    #[inline]
+    fn transfer_unwind(&self, _bbid: BbId, _bb: &BasicBlock<S, C, BbId>, _fact: &mut Self::Fact) {}
+}
+
+/// Per-statement transfer function, implemented by statement and condjmp types.
+pub trait Transfer<F> {
+    fn transfer(&self, fact: &mut F);
+}
+
+impl<F, T> Transfer<F> for crate::jump::Dummy<T> {
+    #[inline]
+    fn transfer(&self, _fact: &mut F) {}
+}
+
+/// Applies the [`Transfer`] functions of all statements and the `condjmp`
+/// of the BB, in the given direction. Placeholders are left untouched.
+pub fn transfer_statements<S, C, T, F>(direction: Direction, bb: &BasicBlock<S, C, T>, fact: &mut F)
+where
+    S: Transfer<F>,
+    C: Transfer<F>,
+{
+    if let BasicBlockInner::Concrete {
+        statements,
+        condjmp,
+        ..
+    } = &bb.inner
+    {
+        match direction {
+            Direction::Forward => {
+                for i in statements {
+                    i.transfer(fact);
+                }
+                if let Some(c) = condjmp {
+                    c.transfer(fact);
+                }
+            }
+            Direction::Backward => {
+                if let Some(c) = condjmp {
+                    c.transfer(fact);
+                }
+                for i in statements.iter().rev() {
+                    i.transfer(fact);
+                }
+            }
+        }
+    }
+}
+
+/// Applies the [`Transfer`] functions of the statements of the BB along its
+/// unwind edge, in the given direction: the result is the join of the facts
+/// right before every statement (forward), or of the facts which reach the
+/// start of the BB from the landing pad via every statement (backward).
+/// BBs without statements can't unwind, thus this results in bottom then.
+pub fn transfer_unwind_statements<S, C, T, F>(
+    direction: Direction,
+    bb: &BasicBlock<S, C, T>,
+    fact: &mut F,
+) where
+    S: Transfer<F>,
+    F: Lattice,
+{
+    let statements = match &bb.inner {
+        BasicBlockInner::Concrete { statements, .. } if !statements.is_empty() => statements,
+        _ => {
+            *fact = F::bottom();
+            return;
+        }
+    };
+    match direction {
+        Direction::Forward => {
+            let mut acc = F::bottom();
+            for i in statements {
+                acc.join(fact);
+                i.transfer(fact);
+            }
+            *fact = acc;
+        }
+        Direction::Backward => {
+            let pad = fact.clone();
+            for i in statements[..statements.len() - 1].iter().rev() {
+                i.transfer(fact);
+                fact.join(&pad);
+            }
+        }
+    }
+}
+
+/// The fixpoint of a dataflow analysis, containing the facts at the
+/// entry (`ins`) and exit (`outs`) of every reachable BB,
+/// independent of the direction of the analysis. Facts along unwind
+/// edges are included in the entry fact of the landing pad (forward)
+/// or of the unwinding BB (backward), but not in any exit fact.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Results<F> {
+    pub ins: Map<BbId, F>,
+    pub outs: Map<BbId, F>,
+}
+
+impl<F> Results<F> {
+    #[inline]
+    pub fn get_in(&self, bbid: BbId) -> Option<&F> {
+        self.ins.get(&bbid)
+    }
+
+    #[inline]
+    pub fn get_out(&self, bbid: BbId) -> Option<&F> {
+        self.outs.get(&bbid)
+    }
+}
+
+/// Solves the given dataflow analysis over all BBs of the CFG.
+///
+/// The worklist is processed in reverse postorder (forward)
+/// or postorder (backward) until a fixpoint is reached.
+pub fn solve<S, C, A>(cfg: &Cfg, arena: &Arena<S, C>, analysis: &A) -> Results<A::Fact>
+where
+    A: Analysis<S, C>,
+{
+    let forward = A::DIRECTION == Direction::Forward;
+    let mut order = cfg.rpo().to_vec();
+    if !forward {
+        order.reverse();
+    }
+    let prio: Map<BbId, usize> = order.iter().enumerate().map(|(n, &i)| (i, n)).collect();
+
+    // `before` is the input of the transfer function, `after` its output
+    let mut before: Map<BbId, A::Fact> = order.iter().map(|&i| (i, A::Fact::bottom())).collect();
+    let mut after = before.clone();
+    let mut worklist: BTreeSet<usize> = (0..order.len()).collect();
+
+    while let Some(n) = worklist.iter().next().copied() {
+        worklist.remove(&n);
+        let bbid = order[n];
+        let (sources, sinks, is_boundary) = if forward {
+            (
+                cfg.preds(bbid),
+                cfg.succs(bbid),
+                cfg.entries().contains(&bbid),
+            )
+        } else {
+            (
+                cfg.succs(bbid),
+                cfg.preds(bbid),
+                cfg.exits().binary_search(&bbid).is_ok(),
+            )
+        };
+
+        let bb = &arena.bbs()[&bbid];
+        let mut fact = if is_boundary {
+            analysis.boundary(bbid)
+        } else {
+            A::Fact::bottom()
+        };
+        for &i in sources {
+            let (from, to) = if forward { (i, bbid) } else { (bbid, i) };
+            if forward && cfg.unwind(from) == Some(to) {
+                // the BB may unwind before any statement, thus start with its input
+                let mut x = before[&from].clone();
+                analysis.transfer_unwind(from, &arena.bbs()[&from], &mut x);
+                fact.join(&x);
+            }
+            if cfg.is_unwind_edge(from, to) {
+                continue;
+            }
+            if let Some(x) = after.get(&i) {
+                fact.join(x);
+            }
+        }
+        if before[&bbid] != fact {
+            if let Some(pad) = cfg.unwind(bbid).filter(|_| forward) {
+                worklist.extend(prio.get(&pad));
+            }
+            before.insert(bbid, fact.clone());
+        }
+
+        analysis.transfer_bb(bbid, bb, &mut fact);
+        if let Some(pad) = cfg.unwind(bbid).filter(|_| !forward) {
+            let mut x = after[&pad].clone();
+            analysis.transfer_unwind(bbid, bb, &mut x);
+            fact.join(&x);
+        }
+        if after[&bbid] != fact {
+            after.insert(bbid, fact);
+            worklist.extend(sinks.iter().filter_map(|i| prio.get(i)));
+        }
+    }
+
+    if forward {
+        Results {
+            ins: before,
+            outs: after,
+        }
+    } else {
+        Results {
+            ins: after,
+            outs: before,
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use super::Cfg;
+use crate::BbId;
+use alloc::collections::{BTreeMap as Map, BTreeSet};
+use alloc::vec::Vec;
+
+const UNDEF: usize = usize::MAX;
+
+/// A generic dominator tree over dense node indices,
+/// with a virtual root (index 0) above all real roots.
+///
+/// Real node `i` (in reverse postorder) gets the index `i + 1`.
+#[derive(Clone, Debug)]
+pub(crate) struct DomTree {
+    nodes: Vec<BbId>,
+    index: Map<BbId, usize>,
+    idom: Vec<usize>,
+    children: Vec<Vec<usize>>,
+    pre: Vec<usize>,
+    post: Vec<usize>,
+}
+
+impl DomTree {
+    /// `rpo` must contain all nodes in reverse postorder (starting at the roots),
+    /// `preds` must return the predecessors of every node and
+    /// `is_root` should return true for every node reachable from the virtual root.
+    pub(crate) fn new<'a, P, R>(rpo: &[BbId], preds: P, is_root: R) -> Self
+    where
+        P: Fn(BbId) -> &'a [BbId],
+        R: Fn(BbId) -> bool,
+    {
+        let nodes = rpo.to_vec();
+        let index: Map<BbId, usize> = nodes.iter().enumerate().map(|(n, &i)| (i, n + 1)).collect();
+        let dpreds: Vec<Vec<usize>> = nodes
+            .iter()
+            .map(|&i| {
+                let mut ps: Vec<usize> = preds(i)
+                    .iter()
+                    .filter_map(|p| index.get(p).copied())
+                    .collect();
+                if is_root(i) {
+                    ps.push(0);
+                }
+                ps
+            })
+            .collect();
+
+        // "A Simple, Fast Dominance Algorithm" by Cooper, Harvey and Kennedy
+        let mut idom = alloc::vec![UNDEF; nodes.len() + 1];
+        idom[0] = 0;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for b in 1..=nodes.len() {
+                let mut new_idom = UNDEF;
+                for &p in dpreds[b - 1].iter() {
+                    if idom[p] == UNDEF {
+                        continue;
+                    }
+                    new_idom = if new_idom == UNDEF {
+                        p
+                    } else {
+                        intersect(&idom, p, new_idom)
+                    };
+                }
+                if idom[b] != new_idom {
+                    idom[b] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut children = alloc::vec![Vec::new(); nodes.len() + 1];
+        for b in 1..=nodes.len() {
+            if idom[b] != UNDEF {
+                children[idom[b]].push(b);
+            }
+        }
+
+        // number the tree nodes to allow constant-time dominance queries
+        let mut pre = alloc::vec![UNDEF; nodes.len() + 1];
+        let mut post = alloc::vec![UNDEF; nodes.len() + 1];
+        let mut cnt = 0;
+        let mut stack = alloc::vec![(0, 0)];
+        pre[0] = cnt;
+        while let Some((n, pos)) = stack.last_mut() {
+            if let Some(&c) = children[*n].get(*pos) {
+                *pos += 1;
+                cnt += 1;
+                pre[c] = cnt;
+                stack.push((c, 0));
+            } else {
+                post[*n] = cnt;
+                stack.pop();
+            }
+        }
+
+        Self {
+            nodes,
+            index,
+            idom,
+            children,
+            pre,
+            post,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn contains(&self, bbid: BbId) -> bool {
+        self.index.contains_key(&bbid)
+    }
+
+    pub(crate) fn idom(&self, bbid: BbId) -> Option<BbId> {
+        match self.idom[*self.index.get(&bbid)?] {
+            0 | UNDEF => None,
+            i => Some(self.nodes[i - 1]),
+        }
+    }
+
+    pub(crate) fn dominates(&self, a: BbId, b: BbId) -> bool {
+        match (self.index.get(&a), self.index.get(&b)) {
+            (Some(&a), Some(&b)) => self.pre[a] <= self.pre[b] && self.post[b] <= self.post[a],
+            _ => false,
+        }
+    }
+
+    pub(crate) fn roots(&self) -> impl Iterator<Item = BbId> + '_ {
+        self.children[0].iter().map(move |&i| self.nodes[i - 1])
+    }
+
+    pub(crate) fn children(&self, bbid: BbId) -> impl Iterator<Item = BbId> + '_ {
+        self.index
+            .get(&bbid)
+            .map(|&i| self.children[i].as_slice())
+            .unwrap_or(&[])
+            .iter()
+            .map(move |&i| self.nodes[i - 1])
+    }
+
+    /// Calculates the dominance frontier of every node.
+    pub(crate) fn frontiers<'a, P>(&self, preds: P) -> Map<BbId, BTreeSet<BbId>>
+    where
+        P: Fn(BbId) -> &'a [BbId],
+    {
+        let mut ret: Map<BbId, BTreeSet<BbId>> =
+            self.nodes.iter().map(|&i| (i, BTreeSet::new())).collect();
+        for (b, &bbid) in self.nodes.iter().enumerate() {
+            let b = b + 1;
+            let stop = self.idom[b];
+            for p in preds(bbid) {
+                let mut runner = match self.index.get(p) {
+                    Some(&r) => r,
+                    None => continue,
+                };
+                while runner != stop && runner != 0 && runner != UNDEF {
+                    ret.get_mut(&self.nodes[runner - 1]).unwrap().insert(bbid);
+                    runner = self.idom[runner];
+                }
+            }
+        }
+        ret
+    }
+}
+
+fn intersect(idom: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while a > b {
+            a = idom[a];
+        }
+        while b > a {
+            b = idom[b];
+        }
+    }
+    a
+}
+
+/// Dominator tree and dominance frontiers of a [`Cfg`].
+///
+/// Every entry point of the CFG is a root of the dominator tree,
+/// BBs which aren't reachable from any entry point are not part of it.
+#[derive(Clone, Debug)]
+pub struct Dominators {
+    tree: DomTree,
+    frontiers: Map<BbId, BTreeSet<BbId>>,
+}
+
+impl Dominators {
+    pub fn new(cfg: &Cfg) -> Self {
+        let entries = cfg.entries();
+        let tree = DomTree::new(cfg.rpo(), |i| cfg.preds(i), |i| entries.contains(&i));
+        let frontiers = tree.frontiers(|i| cfg.preds(i));
+        Self { tree, frontiers }
+    }
+
+    /// Returns `true` if the given BB is reachable from any entry point.
+    #[inline]
+    pub fn contains(&self, bbid: BbId) -> bool {
+        self.tree.contains(bbid)
+    }
+
+    /// Returns the immediate dominator of the given BB,
+    /// or `None` if it is an entry point or unreachable.
+    #[inline]
+    pub fn idom(&self, bbid: BbId) -> Option<BbId> {
+        self.tree.idom(bbid)
+    }
+
+    /// Returns `true` if every path from an entry point to `b` goes through `a`.
+    /// Every reachable BB dominates itself.
+    #[inline]
+    pub fn dominates(&self, a: BbId, b: BbId) -> bool {
+        self.tree.dominates(a, b)
+    }
+
+    #[inline]
+    pub fn strictly_dominates(&self, a: BbId, b: BbId) -> bool {
+        a != b && self.dominates(a, b)
+    }
+
+    /// Returns the roots of the dominator tree.
+    #[inline]
+    pub fn roots(&self) -> impl Iterator<Item = BbId> + '_ {
+        self.tree.roots()
+    }
+
+    /// Returns the BBs which are immediately dominated by the given BB.
+    #[inline]
+    pub fn children(&self, bbid: BbId) -> impl Iterator<Item = BbId> + '_ {
+        self.tree.children(bbid)
+    }
+
+    /// Returns the dominance frontier of the given BB.
+    pub fn frontier(&self, bbid: BbId) -> impl Iterator<Item = BbId> + '_ {
+        self.frontiers.get(&bbid).into_iter().flatten().copied()
+    }
+}
@@ -0,0 +1,191 @@
+use super::{Cfg, LoopForest};
+use crate::bb::BasicBlockInner;
+use crate::jump::{ForeachTarget, Unconditional};
+use crate::{Arena, BbId};
+use alloc::collections::BTreeMap as Map;
+use alloc::vec::Vec;
+
+/// Upper bound for the estimated number of iterations of a loop per entry.
+const MAX_LOOP_SCALE: f64 = 4096.0;
+
+/// Estimated execution frequencies of the BBs of a [`Cfg`], relative to
+/// a single execution of each entry point.
+///
+/// The probabilities of the outgoing edges of a BB are derived from its
+/// [`BranchWeights`](crate::BranchWeights), or are distributed uniformly
+/// if there are none. The conditional jump is taken with the probability
+/// given by its weights, the remaining probability is split between the
+/// targets of the terminator. Jump targets inside of statements and
+/// unwind targets are considered to be never taken.
+///
+/// Loops are handled by scaling the frequency of every loop header with
+/// the expected number of iterations, derived from the probability of
+/// getting back to the header (inner loops first). Edges which close
+/// irreducible cycles are ignored.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlockFrequency {
+    freqs: Map<BbId, f64>,
+    probs: Map<BbId, Vec<(BbId, f64)>>,
+}
+
+/// Calculates the probabilities of the outgoing edges of the given BB.
+fn edge_probs<S, C>(arena: &Arena<S, C>, bbid: BbId) -> Vec<(BbId, f64)>
+where
+    C: ForeachTarget<JumpTarget = BbId>,
+{
+    let bbs = arena.bbs();
+    let (condjmp, next) = match &bbs[&bbid].inner {
+        BasicBlockInner::Concrete { condjmp, next, .. } => (condjmp, next),
+        BasicBlockInner::Placeholder { .. } => return Vec::new(),
+    };
+    let weights = arena
+        .branch_weights(bbid)
+        .filter(|w| w.fits(&bbs[&bbid].inner));
+    let mut ret: Vec<(BbId, f64)> = Vec::new();
+    let mut add = |t: BbId, p: f64| {
+        if !bbs.contains_key(&t) || p <= 0.0 {
+            return;
+        }
+        match ret.iter_mut().find(|i| i.0 == t) {
+            Some(i) => i.1 += p,
+            None => ret.push((t, p)),
+        }
+    };
+
+    let mut rest = 1.0;
+    if let Some(c) = condjmp {
+        let mut targets = Vec::new();
+        c.foreach_target(|&t| targets.push(t));
+        if !targets.is_empty() {
+            let p = match weights.and_then(|w| w.condjmp) {
+                Some((taken, not_taken)) if taken != 0 || not_taken != 0 => {
+                    f64::from(taken) / (f64::from(taken) + f64::from(not_taken))
+                }
+                _ => 0.5,
+            };
+            let n = targets.len() as f64;
+            for t in targets {
+                add(t, p / n);
+            }
+            rest -= p;
+        }
+    }
+
+    let mut targets = Vec::new();
+    let mut tweights = None;
+    match next {
+        Unconditional::Jump(t) => targets.push(*t),
+        Unconditional::Switch(sw) => {
+            sw.foreach_target(|&t| targets.push(t));
+            tweights = weights.and_then(|w| w.switch.as_deref());
+        }
+        Unconditional::Indirect(Some(ts)) => targets.extend_from_slice(ts),
+        Unconditional::Indirect(None) => targets.extend(arena.labels().values().copied()),
+        Unconditional::Call(c) => targets.push(c.continuation),
+        Unconditional::Halt | Unconditional::Return | Unconditional::Unknown => {}
+    }
+    let total: f64 = tweights.map_or(0.0, |w| w.iter().map(|&i| f64::from(i)).sum());
+    for (n, &t) in targets.iter().enumerate() {
+        let share = match tweights {
+            Some(w) if total > 0.0 => f64::from(w[n]) / total,
+            _ => 1.0 / targets.len() as f64,
+        };
+        add(t, rest * share);
+    }
+    ret
+}
+
+impl BlockFrequency {
+    pub fn new<S, C>(cfg: &Cfg, loops: &LoopForest, arena: &Arena<S, C>) -> Self
+    where
+        C: ForeachTarget<JumpTarget = BbId>,
+    {
+        let rpo = cfg.rpo();
+        let rpo_idx: Map<BbId, usize> = rpo.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+        let probs: Map<BbId, Vec<(BbId, f64)>> =
+            rpo.iter().map(|&n| (n, edge_probs(arena, n))).collect();
+        let prob =
+            |from: BbId, to: BbId| probs[&from].iter().find(|i| i.0 == to).map_or(0.0, |i| i.1);
+
+        // scale factor of each loop header, calculated innermost loops first
+        let mut scale: Map<BbId, f64> = Map::new();
+
+        // propagates the frequencies along all forward edges inside of
+        // the region, starting with the given frequencies of the roots
+        let propagate = |region: &mut dyn Iterator<Item = BbId>,
+                         roots: &Map<BbId, f64>,
+                         scale: &Map<BbId, f64>| {
+            let mut freqs: Map<BbId, f64> = Map::new();
+            for n in region {
+                let mut f = roots.get(&n).copied().unwrap_or(0.0);
+                for &p in cfg.preds(n) {
+                    if rpo_idx[&p] < rpo_idx[&n] {
+                        if let Some(pf) = freqs.get(&p) {
+                            f += pf * prob(p, n);
+                        }
+                    }
+                }
+                if let Some(s) = scale.get(&n) {
+                    f *= s;
+                }
+                freqs.insert(n, f);
+            }
+            freqs
+        };
+
+        let mut order: Vec<_> = loops.loops().iter().collect();
+        order.sort_by_key(|l| core::cmp::Reverse(l.depth));
+        for l in order {
+            let mut roots = Map::new();
+            roots.insert(l.header, 1.0);
+            let freqs = propagate(
+                &mut rpo.iter().copied().filter(|i| l.body.contains(i)),
+                &roots,
+                &scale,
+            );
+            let cyclic: f64 = l
+                .latches
+                .iter()
+                .map(|&i| freqs[&i] * prob(i, l.header))
+                .sum();
+            let s = if cyclic < 1.0 - 1.0 / MAX_LOOP_SCALE {
+                1.0 / (1.0 - cyclic)
+            } else {
+                MAX_LOOP_SCALE
+            };
+            scale.insert(l.header, s);
+        }
+
+        let roots = cfg.entries().iter().map(|&i| (i, 1.0)).collect();
+        let freqs = propagate(&mut rpo.iter().copied(), &roots, &scale);
+        Self { freqs, probs }
+    }
+
+    /// Returns the estimated frequency of the given BB,
+    /// or `None` if it isn't part of the CFG.
+    #[inline]
+    pub fn frequency(&self, bbid: BbId) -> Option<f64> {
+        self.freqs.get(&bbid).copied()
+    }
+
+    /// Returns the estimated frequencies of all BBs of the CFG.
+    #[inline(always)]
+    pub fn frequencies(&self) -> &Map<BbId, f64> {
+        &self.freqs
+    }
+
+    /// Returns the probability of taking the edge `from -> to`
+    /// after entering `from`.
+    pub fn edge_probability(&self, from: BbId, to: BbId) -> f64 {
+        self.probs
+            .get(&from)
+            .and_then(|ps| ps.iter().find(|i| i.0 == to))
+            .map_or(0.0, |i| i.1)
+    }
+
+    /// Returns the estimated frequency of the edge `from -> to`.
+    #[inline]
+    pub fn edge_frequency(&self, from: BbId, to: BbId) -> f64 {
+        self.frequency(from).unwrap_or(0.0) * self.edge_probability(from, to)
+    }
+}
@@ -0,0 +1,136 @@
+use super::dataflow::{self, Analysis, Direction, Results};
+use super::Cfg;
+use crate::bb::{BasicBlock, BasicBlockInner};
+use crate::var::ForeachVar;
+use crate::{Arena, BbId};
+use alloc::collections::BTreeSet;
+
+struct LiveVars<'a, V> {
+    live_at_exit: &'a BTreeSet<V>,
+}
+
+/// Applies the effect of a single statement on a set of live variables.
+fn transfer_live<X: ForeachVar>(x: &X, live: &mut BTreeSet<X::Var>)
+where
+    X::Var: Clone + Ord,
+{
+    x.foreach_def(|v| {
+        live.remove(v);
+    });
+    x.foreach_use(|v| {
+        live.insert(v.clone());
+    });
+}
+
+impl<S, C, V> Analysis<S, C> for LiveVars<'_, V>
+where
+    S: ForeachVar<Var = V>,
+    C: ForeachVar<Var = V>,
+    V: Clone + Ord,
+{
+    type Fact = BTreeSet<V>;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self, _bbid: BbId) -> Self::Fact {
+        self.live_at_exit.clone()
+    }
+
+    fn transfer_bb(&self, _bbid: BbId, bb: &BasicBlock<S, C, BbId>, live: &mut Self::Fact) {
+        if let BasicBlockInner::Concrete {
+            statements,
+            condjmp,
+            ..
+        } = &bb.inner
+        {
+            if let Some(c) = condjmp {
+                transfer_live(c, live);
+            }
+            for i in statements.iter().rev() {
+                transfer_live(i, live);
+            }
+        }
+    }
+}
+
+/// Live variables at the entry and exit of every reachable BB.
+///
+/// A variable is live at some point if it may be read later on,
+/// before it is written again. Because every statement of a BB may unwind,
+/// the variables which are live at the entry of its landing pad are live
+/// in front of every statement of it, too.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Liveness<V> {
+    results: Results<BTreeSet<V>>,
+}
+
+impl<V: Clone + Ord> Liveness<V> {
+    /// Computes the liveness, assuming that no variable is live after any exit.
+    pub fn new<S, C>(cfg: &Cfg, arena: &Arena<S, C>) -> Self
+    where
+        S: ForeachVar<Var = V>,
+        C: ForeachVar<Var = V>,
+    {
+        Self::with_live_at_exit(cfg, arena, &BTreeSet::new())
+    }
+
+    /// Computes the liveness, assuming that `live_at_exit` is live after every exit.
+    pub fn with_live_at_exit<S, C>(
+        cfg: &Cfg,
+        arena: &Arena<S, C>,
+        live_at_exit: &BTreeSet<V>,
+    ) -> Self
+    where
+        S: ForeachVar<Var = V>,
+        C: ForeachVar<Var = V>,
+    {
+        Self {
+            results: dataflow::solve(cfg, arena, &LiveVars { live_at_exit }),
+        }
+    }
+
+    #[inline]
+    pub fn live_in(&self, bbid: BbId) -> Option<&BTreeSet<V>> {
+        self.results.get_in(bbid)
+    }
+
+    #[inline]
+    pub fn live_out(&self, bbid: BbId) -> Option<&BTreeSet<V>> {
+        self.results.get_out(bbid)
+    }
+
+    /// Calls `f` for every statement of the given BB in reverse order,
+    /// together with the set of variables which are live directly after it
+    /// (including the ones live at the landing pad, unless it is the last one).
+    /// Useful to find dead statements.
+    pub fn foreach_live_after<S, C, F>(&self, bbid: BbId, bb: &BasicBlock<S, C, BbId>, mut f: F)
+    where
+        S: ForeachVar<Var = V>,
+        C: ForeachVar<Var = V>,
+        F: FnMut(usize, &S, &BTreeSet<V>),
+    {
+        let mut live = match self.live_out(bbid) {
+            Some(x) => x.clone(),
+            None => return,
+        };
+        if let BasicBlockInner::Concrete {
+            statements,
+            condjmp,
+            unwind,
+            ..
+        } = &bb.inner
+        {
+            let pad = unwind.and_then(|i| self.live_in(i));
+            if let Some(c) = condjmp {
+                transfer_live(c, &mut live);
+            }
+            for (n, i) in statements.iter().enumerate().rev() {
+                f(n, i, &live);
+                transfer_live(i, &mut live);
+                if let Some(pad) = pad {
+                    live.extend(pad.iter().cloned());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use super::{Cfg, Dominators};
+use crate::BbId;
+use alloc::collections::{BTreeMap as Map, BTreeSet};
+use alloc::vec::Vec;
+
+/// Index of a [`Loop`] inside of a [`LoopForest`].
+pub type LoopId = usize;
+
+/// A natural loop, consisting of all back edges which share the same header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Loop {
+    /// the single entry BB of the loop, dominates every BB of the loop
+    pub header: BbId,
+
+    /// sources of the back edges to the header
+    pub latches: BTreeSet<BbId>,
+
+    /// all BBs of the loop, including the header and nested loops
+    pub body: BTreeSet<BbId>,
+
+    /// BBs outside of the loop which are targets of edges from inside the loop
+    pub exits: BTreeSet<BbId>,
+
+    /// the innermost loop which contains this loop
+    pub parent: Option<LoopId>,
+
+    /// loops directly nested inside of this loop
+    pub children: Vec<LoopId>,
+
+    /// nesting depth, outermost loops have depth 1
+    pub depth: usize,
+}
+
+/// A strongly connected region with multiple entries, which thus
+/// doesn't form a natural loop.
+///
+/// If the region is nested inside of a natural loop, it is computed inside
+/// of the body of that loop without its header, thus the region doesn't
+/// include the enclosing loop via its back edges.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IrreducibleRegion {
+    /// BBs of the region which are reachable from outside of it
+    pub entries: BTreeSet<BbId>,
+
+    /// all BBs of the strongly connected component containing the entries,
+    /// restricted to the innermost enclosing loop
+    pub body: BTreeSet<BbId>,
+}
+
+/// Loop nesting forest of a [`Cfg`].
+///
+/// Loops are sorted by size (largest first), so a parent loop always
+/// has a smaller [`LoopId`] than its children.
+#[derive(Clone, Debug, Default)]
+pub struct LoopForest {
+    loops: Vec<Loop>,
+    roots: Vec<LoopId>,
+    innermost: Map<BbId, LoopId>,
+    by_header: Map<BbId, LoopId>,
+    back_edges: Vec<(BbId, BbId)>,
+    irreducible: Vec<IrreducibleRegion>,
+}
+
+/// Returns all nodes reachable from `start` by following `next`,
+/// without leaving the nodes accepted by `within`.
+fn reachable<'a, F, W>(start: BbId, next: F, within: W) -> BTreeSet<BbId>
+where
+    F: Fn(BbId) -> &'a [BbId],
+    W: Fn(BbId) -> bool,
+{
+    let mut ret = BTreeSet::new();
+    let mut todo = alloc::vec![start];
+    while let Some(i) = todo.pop() {
+        if ret.insert(i) {
+            todo.extend(next(i).iter().copied().filter(|&j| within(j)));
+        }
+    }
+    ret
+}
+
+impl LoopForest {
+    pub fn new(cfg: &Cfg, dom: &Dominators) -> Self {
+        let mut ret = Self::default();
+
+        // find all retreating edges via DFS
+        let mut retreating = Vec::new();
+        let mut visited = BTreeSet::new();
+        let mut on_stack = BTreeSet::new();
+        let mut stack: Vec<(BbId, usize)> = Vec::new();
+        for &root in cfg.entries() {
+            if !visited.insert(root) {
+                continue;
+            }
+            on_stack.insert(root);
+            stack.push((root, 0));
+            while let Some((n, pos)) = stack.last_mut() {
+                let n = *n;
+                if let Some(&s) = cfg.succs(n).get(*pos) {
+                    *pos += 1;
+                    if visited.insert(s) {
+                        on_stack.insert(s);
+                        stack.push((s, 0));
+                    } else if on_stack.contains(&s) {
+                        retreating.push((n, s));
+                    }
+                } else {
+                    on_stack.remove(&n);
+                    stack.pop();
+                }
+            }
+        }
+        retreating.sort_unstable();
+
+        let mut latches: Map<BbId, BTreeSet<BbId>> = Map::new();
+        let mut irr_entries = BTreeSet::new();
+        for &(from, to) in retreating.iter() {
+            if dom.dominates(to, from) {
+                ret.back_edges.push((from, to));
+                latches.entry(to).or_default().insert(from);
+            } else {
+                irr_entries.insert(to);
+            }
+        }
+
+        // collect the natural loops
+        for (header, latches) in latches {
+            let mut body = BTreeSet::new();
+            body.insert(header);
+            let mut todo: Vec<_> = latches.iter().copied().collect();
+            while let Some(i) = todo.pop() {
+                if body.insert(i) {
+                    todo.extend_from_slice(cfg.preds(i));
+                }
+            }
+            let exits = body
+                .iter()
+                .flat_map(|&i| cfg.succs(i).iter().copied())
+                .filter(|i| !body.contains(i))
+                .collect();
+            ret.loops.push(Loop {
+                header,
+                latches,
+                body,
+                exits,
+                parent: None,
+                children: Vec::new(),
+                depth: 0,
+            });
+        }
+        ret.loops.sort_by(|a, b| {
+            b.body
+                .len()
+                .cmp(&a.body.len())
+                .then(a.header.cmp(&b.header))
+        });
+
+        // build the nesting forest; natural loops are either disjoint or nested
+        for id in 0..ret.loops.len() {
+            let header = ret.loops[id].header;
+            let parent = ret.innermost.get(&header).copied();
+            let depth = match parent {
+                Some(p) => {
+                    ret.loops[p].children.push(id);
+                    ret.loops[p].depth + 1
+                }
+                None => {
+                    ret.roots.push(id);
+                    1
+                }
+            };
+            let l = &mut ret.loops[id];
+            l.parent = parent;
+            l.depth = depth;
+            for &i in l.body.iter() {
+                ret.innermost.insert(i, id);
+            }
+            ret.by_header.insert(header, id);
+        }
+
+        // group the irreducible retreating edges by their strongly connected
+        // component inside of the innermost loop which doesn't start at them
+        let mut domains: Vec<Option<LoopId>> = Vec::new();
+        for entry in irr_entries {
+            let mut domain = ret.innermost.get(&entry).copied();
+            while let Some(l) = domain.filter(|&l| ret.loops[l].header == entry) {
+                domain = ret.loops[l].parent;
+            }
+            let covered = ret
+                .irreducible
+                .iter()
+                .zip(domains.iter())
+                .any(|(r, &d)| d == domain && r.body.contains(&entry));
+            if covered {
+                continue;
+            }
+            let within = |i: BbId| match domain {
+                Some(l) => ret.loops[l].header != i && ret.loops[l].body.contains(&i),
+                None => true,
+            };
+            let fwd = reachable(entry, |i| cfg.succs(i), within);
+            let body: BTreeSet<_> = reachable(entry, |i| cfg.preds(i), within)
+                .intersection(&fwd)
+                .copied()
+                .collect();
+            let entries = body
+                .iter()
+                .copied()
+                .filter(|i| {
+                    cfg.entries().contains(i) || cfg.preds(*i).iter().any(|p| !body.contains(p))
+                })
+                .collect();
+            ret.irreducible.push(IrreducibleRegion { entries, body });
+            domains.push(domain);
+        }
+
+        ret
+    }
+
+    #[inline(always)]
+    pub fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    #[inline]
+    pub fn get(&self, id: LoopId) -> Option<&Loop> {
+        self.loops.get(id)
+    }
+
+    /// Returns the outermost loops.
+    #[inline(always)]
+    pub fn roots(&self) -> &[LoopId] {
+        &self.roots
+    }
+
+    /// Returns the innermost loop which contains the given BB.
+    #[inline]
+    pub fn innermost_loop(&self, bbid: BbId) -> Option<LoopId> {
+        self.innermost.get(&bbid).copied()
+    }
+
+    /// Returns the number of loops which contain the given BB.
+    pub fn loop_depth(&self, bbid: BbId) -> usize {
+        self.innermost_loop(bbid)
+            .map(|i| self.loops[i].depth)
+            .unwrap_or(0)
+    }
+
+    /// Returns the loop with the given header.
+    #[inline]
+    pub fn loop_of_header(&self, bbid: BbId) -> Option<LoopId> {
+        self.by_header.get(&bbid).copied()
+    }
+
+    /// Returns all back edges `(latch, header)`, sorted.
+    #[inline(always)]
+    pub fn back_edges(&self) -> &[(BbId, BbId)] {
+        &self.back_edges
+    }
+
+    /// Returns all irreducible regions, these aren't part of any natural loop
+    /// unless they are nested inside of one.
+    #[inline(always)]
+    pub fn irreducible(&self) -> &[IrreducibleRegion] {
+        &self.irreducible
+    }
+
+    #[inline]
+    pub fn is_reducible(&self) -> bool {
+        self.irreducible.is_empty()
+    }
+}
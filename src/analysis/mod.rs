@@ -0,0 +1,23 @@
+//! Control flow analyses over an [`Arena`](crate::Arena).
+//!
+//! All analyses operate on a [`Cfg`], which is a snapshot of the
+//! control flow graph of an arena, restricted to the BBs reachable
+//! from a chosen set of entry points. The [`CallGraph`] connects the
+//! functions of an arena, which are split by call terminators.
+
+mod callgraph;
+mod cfg;
+pub mod dataflow;
+mod dom;
+mod freq;
+mod liveness;
+mod loops;
+mod pdom;
+
+pub use callgraph::CallGraph;
+pub use cfg::Cfg;
+pub use dom::Dominators;
+pub use freq::BlockFrequency;
+pub use liveness::Liveness;
+pub use loops::{IrreducibleRegion, Loop, LoopForest, LoopId};
+pub use pdom::{ControlDependence, PostDominators};
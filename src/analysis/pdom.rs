@@ -0,0 +1,96 @@
+use super::cfg::reverse_postorder;
+use super::dom::DomTree;
+use super::Cfg;
+use crate::BbId;
+use alloc::collections::{BTreeMap as Map, BTreeSet};
+
+/// Post-dominator tree of a [`Cfg`].
+///
+/// This is calculated on the reverse CFG, with a virtual exit which joins
+/// all exits of the CFG (see [`Cfg::exits`]). BBs from which no exit is
+/// reachable (e.g. infinite loops) are not part of the tree.
+#[derive(Clone, Debug)]
+pub struct PostDominators {
+    tree: DomTree,
+}
+
+impl PostDominators {
+    pub fn new(cfg: &Cfg) -> Self {
+        let exits = cfg.exits();
+        let rpo = reverse_postorder(exits.iter().copied(), |i| cfg.preds(i));
+        let tree = DomTree::new(&rpo, |i| cfg.succs(i), |i| exits.binary_search(&i).is_ok());
+        Self { tree }
+    }
+
+    /// Returns `true` if any exit is reachable from the given BB.
+    #[inline]
+    pub fn contains(&self, bbid: BbId) -> bool {
+        self.tree.contains(bbid)
+    }
+
+    /// Returns the immediate post-dominator of the given BB, or `None` if it
+    /// is only post-dominated by the virtual exit or not part of the tree.
+    #[inline]
+    pub fn ipdom(&self, bbid: BbId) -> Option<BbId> {
+        self.tree.idom(bbid)
+    }
+
+    /// Returns `true` if every path from `b` to the virtual exit goes through `a`.
+    #[inline]
+    pub fn post_dominates(&self, a: BbId, b: BbId) -> bool {
+        self.tree.dominates(a, b)
+    }
+
+    #[inline]
+    pub fn strictly_post_dominates(&self, a: BbId, b: BbId) -> bool {
+        a != b && self.post_dominates(a, b)
+    }
+
+    /// Returns the roots of the post-dominator tree
+    /// (the children of the virtual exit).
+    #[inline]
+    pub fn roots(&self) -> impl Iterator<Item = BbId> + '_ {
+        self.tree.roots()
+    }
+
+    /// Returns the BBs which are immediately post-dominated by the given BB.
+    #[inline]
+    pub fn children(&self, bbid: BbId) -> impl Iterator<Item = BbId> + '_ {
+        self.tree.children(bbid)
+    }
+}
+
+/// Control dependence graph of a [`Cfg`].
+///
+/// A BB `b` is control dependent on `a` if `a` has a successor which is
+/// post-dominated by `b`, but `b` doesn't strictly post-dominate `a`,
+/// i.e. the branch at the end of `a` decides if `b` gets executed.
+#[derive(Clone, Debug, Default)]
+pub struct ControlDependence {
+    deps: Map<BbId, BTreeSet<BbId>>,
+    dependents: Map<BbId, BTreeSet<BbId>>,
+}
+
+impl ControlDependence {
+    pub fn new(cfg: &Cfg, pdom: &PostDominators) -> Self {
+        // control dependences are the post-dominance frontiers
+        let deps = pdom.tree.frontiers(|i| cfg.succs(i));
+        let mut dependents: Map<BbId, BTreeSet<BbId>> = Map::new();
+        for (&b, ds) in deps.iter() {
+            for &a in ds.iter() {
+                dependents.entry(a).or_default().insert(b);
+            }
+        }
+        Self { deps, dependents }
+    }
+
+    /// Returns the BBs on which the given BB is control dependent.
+    pub fn deps(&self, bbid: BbId) -> impl Iterator<Item = BbId> + '_ {
+        self.deps.get(&bbid).into_iter().flatten().copied()
+    }
+
+    /// Returns the BBs which are control dependent on the given BB.
+    pub fn dependents(&self, bbid: BbId) -> impl Iterator<Item = BbId> + '_ {
+        self.dependents.get(&bbid).into_iter().flatten().copied()
+    }
+}
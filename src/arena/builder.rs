@@ -0,0 +1,165 @@
+use super::*;
+
+/// Incrementally assembles an [`Arena`], allowing BBs to reference
+/// BBs which are defined later on.
+///
+/// Forward references are created either via [`ArenaBuilder::reserve`]
+/// or via [`ArenaBuilder::label`], which hands out the ID associated with
+/// a label, reserving a new one if the label is unknown yet.
+/// Nothing is validated until [`ArenaBuilder::finish`] is called.
+#[derive(Debug)]
+pub struct ArenaBuilder<S, C> {
+    bbs: Map<BbId, ABB<S, C>>,
+    labels: LabelMap,
+    next_id: BbId,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum BuildError {
+    /// A BB ID was referenced (by BBs and/or labels), but never defined.
+    #[cfg_attr(
+        feature = "std",
+        error("BB {bbid} (labels = {labels:?}) is referenced by {referenced_by:?}, but was never defined")
+    )]
+    Undefined {
+        bbid: BbId,
+        labels: Vec<String>,
+        referenced_by: Vec<BbId>,
+    },
+
+    /// A placeholder BB has no label attached, thus can't ever be resolved.
+    #[cfg_attr(feature = "std", error("placeholder BB {0} has no label"))]
+    UnlabeledPlaceholder(BbId),
+}
+
+impl<S, C> Default for ArenaBuilder<S, C> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            bbs: Map::new(),
+            labels: Map::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl<S, C> ArenaBuilder<S, C> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns a fresh BB ID, which should be defined later
+    /// via [`ArenaBuilder::define`].
+    pub fn reserve(&mut self) -> BbId {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    /// Returns the BB ID associated with the given label,
+    /// reserving a new one if the label isn't known yet.
+    pub fn label(&mut self, label: Label) -> BbId {
+        if let Some(&bbid) = self.labels.get(&*label) {
+            return bbid;
+        }
+        let bbid = self.reserve();
+        self.labels.insert(label.into_owned(), bbid);
+        bbid
+    }
+
+    /// Attaches a label to a (possibly not yet defined) BB.
+    /// Fails if the label is already associated with another BB.
+    pub fn set_label(&mut self, label: Label, target: BbId) -> Result<(), SetBbLabelError> {
+        match set_label(&mut self.labels, label.into_owned(), target, false) {
+            Ok(_) => {}
+            Err(SetBbLabelError::LabelAlreadyExists { orig_target }) if orig_target == target => {}
+            Err(e) => return Err(e),
+        }
+        if target >= self.next_id {
+            self.next_id = target + 1;
+        }
+        Ok(())
+    }
+
+    /// Defines the BB with the given ID, which is usually obtained from
+    /// [`ArenaBuilder::reserve`] or [`ArenaBuilder::label`].
+    /// If the BB was already defined, $bb is returned.
+    pub fn define(&mut self, bbid: BbId, bb: ABB<S, C>) -> Result<(), ABB<S, C>> {
+        match self.bbs.entry(bbid) {
+            MapEntry::Occupied(_) => return Err(bb),
+            MapEntry::Vacant(e) => {
+                e.insert(bb);
+            }
+        }
+        if bbid >= self.next_id {
+            self.next_id = bbid + 1;
+        }
+        Ok(())
+    }
+
+    /// Defines a BB under a fresh ID and returns it.
+    pub fn push(&mut self, bb: ABB<S, C>) -> BbId {
+        let bbid = self.reserve();
+        self.bbs.insert(bbid, bb);
+        bbid
+    }
+}
+
+impl<S, C> ArenaBuilder<S, C>
+where
+    ABB<S, C>: ForeachTarget<JumpTarget = BbId>,
+{
+    /// Resolves all references and returns the finished [`Arena`].
+    /// If any reserved ID or label was left undefined, or any placeholder
+    /// has no label, all such problems are returned instead.
+    pub fn finish(self) -> Result<Arena<S, C>, Vec<BuildError>> {
+        let Self { bbs, labels, .. } = self;
+
+        let mut undef: Map<BbId, (Vec<String>, Vec<BbId>)> = Map::new();
+        for (&n, i) in bbs.iter() {
+            i.foreach_target(|t| {
+                if !bbs.contains_key(t) {
+                    let refs = &mut undef.entry(*t).or_default().1;
+                    if refs.last() != Some(&n) {
+                        refs.push(n);
+                    }
+                }
+            });
+        }
+        let mut labeled = alloc::collections::BTreeSet::new();
+        for (label, &t) in labels.iter() {
+            if bbs.contains_key(&t) {
+                labeled.insert(t);
+            } else {
+                undef.entry(t).or_default().0.push(label.clone());
+            }
+        }
+
+        let mut errs: Vec<BuildError> = undef
+            .into_iter()
+            .map(|(bbid, (labels, referenced_by))| BuildError::Undefined {
+                bbid,
+                labels,
+                referenced_by,
+            })
+            .collect();
+        errs.extend(
+            bbs.iter()
+                .filter(|(n, i)| i.inner.is_placeholder() && !labeled.contains(n))
+                .map(|(&n, _)| BuildError::UnlabeledPlaceholder(n)),
+        );
+        if !errs.is_empty() {
+            return Err(errs);
+        }
+
+        let mut ret = Arena {
+            bbs,
+            labels,
+            ..Default::default()
+        };
+        ret.rebuild_preds();
+        Ok(ret)
+    }
+}
@@ -1,6 +1,7 @@
 use super::*;
+use alloc::collections::BTreeSet;
 
-fn check_finish(mut offending: Vec<(BbId, BbId)>) -> Result<(), OffendingIds> {
+pub(super) fn check_finish<I: Idx>(mut offending: Vec<(I, I)>) -> Result<(), OffendingIds<I>> {
     if offending.is_empty() {
         Ok(())
     } else {
@@ -11,19 +12,19 @@ fn check_finish(mut offending: Vec<(BbId, BbId)>) -> Result<(), OffendingIds> {
     }
 }
 
-impl<S, C> Arena<S, C>
+impl<S, C, I: Idx> Arena<S, C, I>
 where
-    ABB<S, C>: ForeachTarget<JumpTarget = BbId>,
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I>,
 {
-    fn check_intern(&self, bbid: BbId, bb: &ABB<S, C>, offending: &mut Vec<(BbId, BbId)>) {
+    pub(super) fn check_intern(&self, bbid: I, bb: &ABB<S, C, I>, offending: &mut Vec<(I, I)>) {
         bb.foreach_target(|&t| {
-            if t != bbid && self.bbs.get(&t).is_none() {
+            if t != bbid && !self.bbs.contains_key(&t) {
                 offending.push((bbid, t));
             }
         });
     }
 
-    fn check_bbs(&self) -> Vec<(BbId, BbId)> {
+    fn check_bbs(&self) -> Vec<(I, I)> {
         let mut errs = Vec::new();
         for (&n, i) in self.bbs.iter() {
             self.check_intern(n, i, &mut errs);
@@ -33,37 +34,46 @@ where
 
     /// Use this method to re-check all references in the `Arena` after
     /// modifications via [`Arena::bbs_mut`].
-    pub fn check(&self) -> Result<(), OffendingIds> {
+    /// The predecessor index isn't touched, see [`Arena::update_predecessors`].
+    pub fn check(&self) -> Result<(), OffendingIds<I>> {
         let mut errs = self.check_bbs();
         // all labels should point to a valid BbId
         errs.extend(self.labels.values().filter_map(|&i| {
-            if self.bbs.get(&i).is_none() {
+            if !self.bbs.contains_key(&i) {
                 Some((i, i))
             } else {
                 None
             }
         }));
         // all placeholders should have label(s)
+        // (the predecessor index is usually stale here, don't scan the labels per BB)
+        let labeled: Option<BTreeSet<I>> = if self.preds_valid {
+            None
+        } else {
+            Some(self.labels.values().copied().collect())
+        };
         for (&n, i) in self.bbs.iter() {
-            if i.inner.is_placeholder() && self.labels_of_bb(n).next().is_none() {
+            let has_label = match &labeled {
+                Some(labeled) => labeled.contains(&n),
+                None => self.labels_of_bb(n).next().is_some(),
+            };
+            if i.inner.is_placeholder() && !has_label {
                 errs.push((n, n));
             }
         }
         check_finish(errs)
     }
 
-    fn find_first_free(&self) -> Option<usize> {
-        for i in self.cache_ins_start..usize::MAX {
-            if self.bbs.get(&i).is_none() {
-                return Some(i);
-            }
-        }
-        None
+    fn find_first_free(&self) -> Option<I> {
+        (self.cache_ins_start..usize::MAX)
+            .map(I::from_index)
+            .find(|i| !self.bbs.contains_key(i))
     }
 
     /// Returns the ID of the newly appended BB if successful,
     /// or $bb & the invalid BbIds.
-    pub fn push(&mut self, bb: ABB<S, C>) -> Result<usize, (ABB<S, C>, OffendingIds)> {
+    #[allow(clippy::type_complexity)]
+    pub fn push(&mut self, bb: ABB<S, C, I>) -> Result<I, (ABB<S, C, I>, OffendingIds<I>)> {
         let ret = match self.find_first_free() {
             Some(n) => n,
             None => return Err((bb, OffendingIds(Vec::new()))),
@@ -72,32 +82,83 @@ where
         self.check_intern(ret, &bb, &mut errs);
         match check_finish(errs) {
             Ok(()) => {
+                self.preds.insert(ret, Default::default());
+                preds::link_bb(&mut self.preds, ret, &bb);
                 self.bbs.insert(ret, bb);
-                self.cache_ins_start = ret.saturating_add(1);
+                self.cache_ins_start = ret.index().saturating_add(1);
                 Ok(ret)
             }
             Err(errs) => Err((bb, errs)),
         }
     }
 
+    /// Allocates a new BB ID, occupied by a (non-extern, unlabeled)
+    /// placeholder until it gets replaced via [`Arena::fill`].
+    /// Other BBs may reference the returned ID right away.
+    ///
+    /// Note that [`Arena::check`] reports reserved but unfilled BBs,
+    /// unless a label got attached to them.
+    pub fn reserve(&mut self) -> I {
+        let bb = BasicBlock {
+            inner: BasicBlockInner::Placeholder { is_extern: false },
+            is_public: false,
+        };
+        match self.push(bb) {
+            Ok(n) => n,
+            Err(_) => panic!("BbId space exhausted"),
+        }
+    }
+
+    /// Replaces the placeholder at $bbid with $bb, after checking that all
+    /// targets of $bb are valid. On failure, returns $bb & the reason.
+    #[allow(clippy::type_complexity)]
+    pub fn fill(&mut self, bbid: I, bb: ABB<S, C, I>) -> Result<(), (ABB<S, C, I>, FillError<I>)> {
+        match self.bbs.get(&bbid) {
+            None => return Err((bb, FillError::InvalidId(bbid))),
+            Some(x) if !x.inner.is_placeholder() => {
+                return Err((bb, FillError::NotPlaceholder(bbid)))
+            }
+            Some(_) => {}
+        }
+        let mut errs = Vec::new();
+        self.check_intern(bbid, &bb, &mut errs);
+        if let Err(errs) = check_finish(errs) {
+            return Err((bb, FillError::Offending(errs)));
+        }
+        // placeholders don't reference anything, thus nothing to unlink
+        preds::link_bb(&mut self.preds, bbid, &bb);
+        self.bbs.insert(bbid, bb);
+        Ok(())
+    }
+
     /// Removes a BB, fails if any references to it exist.
     /// If successful, returns the removed BB and all labels which referenced it.
     /// Otherwise, returns the offending BBs (which still reference it)
-    pub fn remove(&mut self, bbid: BbId) -> Option<Result<(ABB<S, C>, Vec<String>), OffendingIds>> {
-        let x = self.bbs.remove(&bbid)?;
-        let offending = self.check_bbs();
-        Some(if offending.is_empty() {
-            let (labelrt, rlabels) = take(&mut self.labels)
-                .into_iter()
-                .partition(|&(_, v)| v == bbid);
-            self.labels = rlabels;
-            if bbid < self.cache_ins_start {
-                self.cache_ins_start = bbid;
-            }
-            Ok((x, labelrt.into_iter().map(|x| x.0).collect()))
-        } else {
-            self.bbs.insert(bbid, x);
-            Err(OffendingIds(offending))
-        })
+    #[allow(clippy::type_complexity)]
+    pub fn remove(
+        &mut self,
+        bbid: I,
+    ) -> Option<Result<(ABB<S, C, I>, Vec<String>), OffendingIds<I>>> {
+        if !self.bbs.contains_key(&bbid) {
+            return None;
+        }
+        self.ensure_preds();
+        let offending: Vec<_> = self.preds[&bbid]
+            .bbs
+            .iter()
+            .filter(|&&i| i != bbid)
+            .map(|&i| (i, bbid))
+            .collect();
+        if !offending.is_empty() {
+            return Some(Err(OffendingIds(offending)));
+        }
+        let x = self.bbs.remove(&bbid).unwrap();
+        let labelrt = self.preds.remove(&bbid).unwrap().labels;
+        preds::unlink_bb(&mut self.preds, bbid, &x);
+        for i in labelrt.iter() {
+            self.labels.remove(i);
+        }
+        self.free_slot(bbid);
+        Some(Ok((x, labelrt.into_iter().collect())))
     }
 }
@@ -0,0 +1,95 @@
+use super::*;
+use crate::analysis::Cfg;
+
+/// The order in which [`Arena::compact`] assigns new IDs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum CompactOrder {
+    /// keep the current relative order of IDs, which reflects
+    /// the order in which the BBs were inserted
+    Insertion,
+
+    /// reverse postorder of the control flow graph, starting from all
+    /// public and labeled BBs (see [`Cfg::from_arena`])
+    ReversePostorder,
+
+    /// order of the (alphabetically smallest) label of each BB, followed by
+    /// the reverse postorder of all BBs reachable from labeled ones
+    Labels,
+}
+
+impl<S, C, I: Idx> Arena<S, C, I>
+where
+    S: ForeachTarget<JumpTarget = I>,
+    C: ForeachTarget<JumpTarget = I>,
+{
+    /// Renumbers all BBs densely (starting at 0) in the given order,
+    /// rewriting all jump targets and labels. BBs which aren't covered
+    /// by the order (e.g. unreachable or unlabeled BBs) are appended
+    /// afterwards, keeping their relative order.
+    ///
+    /// Returns the mapping (old ID -> new ID).
+    pub fn compact(&mut self, order: CompactOrder) -> Map<I, I> {
+        let mut map: Map<I, I> = Map::new();
+        let mut assign = |bbid: I| {
+            let next = I::from_index(map.len());
+            map.entry(bbid).or_insert(next);
+        };
+        match order {
+            CompactOrder::Insertion => {}
+            CompactOrder::ReversePostorder => {
+                Cfg::from_arena(self)
+                    .rpo()
+                    .iter()
+                    .copied()
+                    .for_each(&mut assign);
+            }
+            CompactOrder::Labels => {
+                let entries: Vec<I> = self
+                    .labels
+                    .values()
+                    .copied()
+                    .filter(|i| self.bbs.contains_key(i))
+                    .collect();
+                entries.iter().copied().for_each(&mut assign);
+                Cfg::new(self, entries)
+                    .rpo()
+                    .iter()
+                    .copied()
+                    .for_each(&mut assign);
+            }
+        }
+        self.bbs.keys().copied().for_each(&mut assign);
+
+        if map.iter().all(|(old, new)| old == new) {
+            return map;
+        }
+        let weights = take(&mut self.weights);
+        for (&old, &new) in map.iter() {
+            if old != new {
+                self.free_slot(old);
+            }
+        }
+        self.weights = weights
+            .into_iter()
+            .filter_map(|(n, w)| map.get(&n).map(|&nn| (nn, w)))
+            .collect();
+        let old = take(&mut self.bbs);
+        for (n, mut bb) in old {
+            bb.foreach_target_mut(|t| {
+                if let Some(&nt) = map.get(t) {
+                    *t = nt;
+                }
+            });
+            self.bbs.insert(map[&n], bb);
+        }
+        for i in self.labels.values_mut() {
+            if let Some(&ni) = map.get(i) {
+                *i = ni;
+            }
+        }
+        self.cache_ins_start = self.bbs.len();
+        self.rebuild_preds();
+        map
+    }
+}
@@ -0,0 +1,199 @@
+use super::*;
+use crate::jump::Unconditional;
+use core::fmt::{self, Write};
+
+/// Renders statements and conditional jumps for [`Arena::to_dot_with`].
+pub trait DotFormatter<S, C> {
+    fn fmt_statement(&self, stmt: &S, w: &mut dyn Write) -> fmt::Result;
+
+    fn fmt_condjmp(&self, condjmp: &C, w: &mut dyn Write) -> fmt::Result;
+}
+
+/// Renders statements and conditional jumps via their [`Debug`](fmt::Debug) impls.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DebugFormatter;
+
+impl<S: fmt::Debug, C: fmt::Debug> DotFormatter<S, C> for DebugFormatter {
+    fn fmt_statement(&self, stmt: &S, w: &mut dyn Write) -> fmt::Result {
+        write!(w, "{:?}", stmt)
+    }
+
+    fn fmt_condjmp(&self, condjmp: &C, w: &mut dyn Write) -> fmt::Result {
+        write!(w, "{:?}", condjmp)
+    }
+}
+
+/// Escapes a string for usage inside of a DOT label,
+/// every line gets left-aligned.
+struct Escaper<'a>(&'a mut String);
+
+impl Write for Escaper<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' | '\\' => {
+                    self.0.push('\\');
+                    self.0.push(c);
+                }
+                '\n' => self.0.push_str("\\l"),
+                _ => self.0.push(c),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S, C> Arena<S, C>
+where
+    S: ForeachTarget<JumpTarget = BbId>,
+    C: ForeachTarget<JumpTarget = BbId>,
+{
+    /// Renders the arena as a Graphviz DOT graph,
+    /// statements are only shown by their count.
+    pub fn to_dot(&self) -> String {
+        self.render_dot(None)
+    }
+
+    /// Renders the arena as a Graphviz DOT graph,
+    /// using `fmtr` to render statements and conditional jumps.
+    pub fn to_dot_with<F>(&self, fmtr: &F) -> String
+    where
+        F: DotFormatter<S, C>,
+    {
+        self.render_dot(Some(fmtr))
+    }
+
+    fn render_dot(&self, fmtr: Option<&dyn DotFormatter<S, C>>) -> String {
+        let mut ret = String::new();
+        // writing into a `String` never fails
+        let _ = self.write_dot(&mut ret, fmtr);
+        ret
+    }
+
+    fn write_dot(&self, ret: &mut String, fmtr: Option<&dyn DotFormatter<S, C>>) -> fmt::Result {
+        ret.push_str("digraph arena {\n  node [shape=box, fontname=monospace];\n");
+        for (&n, bb) in self.bbs.iter() {
+            let mut label = String::new();
+            write!(label, "bb{}", n)?;
+            if bb.is_public {
+                label.push_str(" (pub)");
+            }
+            label.push_str("\\l");
+            for i in self.labels_of_bb(n) {
+                Escaper(&mut label).write_str(i)?;
+                label.push_str(":\\l");
+            }
+
+            let mut attrs = String::new();
+            if bb.is_public {
+                attrs.push_str(", penwidth=2");
+            }
+            match &bb.inner {
+                BasicBlockInner::Concrete {
+                    statements,
+                    condjmp,
+                    next,
+                    unwind,
+                } => {
+                    match fmtr {
+                        Some(fmtr) => {
+                            for i in statements {
+                                label.push_str("  ");
+                                fmtr.fmt_statement(i, &mut Escaper(&mut label))?;
+                                label.push_str("\\l");
+                            }
+                            if let Some(c) = condjmp {
+                                label.push_str("  if ");
+                                fmtr.fmt_condjmp(c, &mut Escaper(&mut label))?;
+                                label.push_str("\\l");
+                            }
+                        }
+                        None if !statements.is_empty() => {
+                            write!(label, "  ({} statements)\\l", statements.len())?;
+                        }
+                        None => {}
+                    }
+                    match next {
+                        Unconditional::Halt => label.push_str("  halt\\l"),
+                        Unconditional::Jump(t) => write!(label, "  jmp bb{}\\l", t)?,
+                        Unconditional::Return => label.push_str("  ret\\l"),
+                        Unconditional::Unknown => label.push_str("  unknown\\l"),
+                        Unconditional::Switch(sw) => {
+                            label.push_str("  switch");
+                            for (i, t) in sw.cases.iter().enumerate() {
+                                let sep = if i == 0 { " " } else { ", " };
+                                write!(label, "{}bb{}", sep, t)?;
+                            }
+                            write!(label, " else bb{}\\l", sw.default)?;
+                        }
+                        Unconditional::Indirect(None) => label.push_str("  ijmp *\\l"),
+                        Unconditional::Indirect(Some(ts)) => {
+                            label.push_str("  ijmp");
+                            for (i, t) in ts.iter().enumerate() {
+                                let sep = if i == 0 { " " } else { ", " };
+                                write!(label, "{}bb{}", sep, t)?;
+                            }
+                            label.push_str("\\l");
+                        }
+                        Unconditional::Call(c) => {
+                            write!(label, "  call bb{} then bb{}\\l", c.callee, c.continuation)?
+                        }
+                    }
+                    if let Some(t) = unwind {
+                        write!(label, "  unwind bb{}\\l", t)?;
+                    }
+                }
+                BasicBlockInner::Placeholder { is_extern } => {
+                    label.push_str(if *is_extern {
+                        "extern placeholder\\l"
+                    } else {
+                        "placeholder\\l"
+                    });
+                    attrs.push_str(", style=dashed");
+                }
+            }
+            writeln!(ret, "  bb{} [label=\"{}\"{}];", n, label, attrs)?;
+        }
+
+        for (&n, bb) in self.bbs.iter() {
+            if let BasicBlockInner::Concrete {
+                statements,
+                condjmp,
+                next,
+                unwind,
+            } = &bb.inner
+            {
+                let mut edges: Vec<(BbId, &str)> = Vec::new();
+                statements.foreach_target(|&t| edges.push((t, "style=dotted")));
+                if let Some(t) = unwind {
+                    edges.push((*t, "style=dotted, label=\"unwind\""));
+                }
+                condjmp.foreach_target(|&t| edges.push((t, "style=dashed, label=\"if\"")));
+                match next {
+                    Unconditional::Switch(_) => {}
+                    Unconditional::Call(c) => {
+                        edges.push((c.callee, "style=bold, label=\"call\""));
+                        edges.push((c.continuation, "style=solid"));
+                    }
+                    _ => next.foreach_target(|&t| edges.push((t, "style=solid"))),
+                }
+                for (t, style) in edges {
+                    writeln!(ret, "  bb{} -> bb{} [{}];", n, t, style)?;
+                }
+                // label the edges of a switch with their case
+                if let Unconditional::Switch(sw) = next {
+                    for (i, t) in sw.cases.iter().enumerate() {
+                        writeln!(ret, "  bb{} -> bb{} [style=solid, label=\"{}\"];", n, t, i)?;
+                    }
+                    writeln!(
+                        ret,
+                        "  bb{} -> bb{} [style=solid, label=\"else\"];",
+                        n, sw.default
+                    )?;
+                }
+            }
+        }
+        ret.push_str("}\n");
+        Ok(())
+    }
+}
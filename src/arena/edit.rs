@@ -0,0 +1,173 @@
+use super::*;
+use core::ops::{Deref, DerefMut};
+
+/// Guard which gives mutable access to BBs of an [`Arena`], and re-validates
+/// the touched BBs on [`ArenaEditor::commit`] or drop.
+///
+/// If any touched BB ends up with an invalid jump target (or as a placeholder
+/// without labels), all edits are rolled back. Unlike [`Arena::bbs_mut`],
+/// this keeps the predecessor index up-to-date and doesn't require a full
+/// [`Arena::check`] afterwards.
+pub struct ArenaEditor<'a, S, C, I = BbId>
+where
+    I: Idx,
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    arena: &'a mut Arena<S, C, I>,
+    // original state of all touched BBs
+    orig: Map<I, ABB<S, C, I>>,
+}
+
+/// Guard which gives mutable access to a single BB,
+/// see [`ArenaEditor`] for details.
+pub struct BbMut<'a, S, C, I = BbId>
+where
+    I: Idx,
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    editor: ArenaEditor<'a, S, C, I>,
+    bbid: I,
+}
+
+impl<S, C, I: Idx> Arena<S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    #[inline]
+    pub fn edit(&mut self) -> ArenaEditor<'_, S, C, I> {
+        ArenaEditor {
+            arena: self,
+            orig: Map::new(),
+        }
+    }
+
+    pub fn bb_mut(&mut self, bbid: I) -> Option<BbMut<'_, S, C, I>> {
+        let mut editor = self.edit();
+        editor.get_mut(bbid)?;
+        Some(BbMut { editor, bbid })
+    }
+}
+
+impl<'a, S, C, I: Idx> ArenaEditor<'a, S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    #[inline]
+    pub fn get(&self, bbid: I) -> Option<&ABB<S, C, I>> {
+        self.arena.bbs.get(&bbid)
+    }
+
+    pub fn get_mut(&mut self, bbid: I) -> Option<&mut ABB<S, C, I>> {
+        let bb = self.arena.bbs.get_mut(&bbid)?;
+        if let MapEntry::Vacant(e) = self.orig.entry(bbid) {
+            e.insert(bb.clone());
+        }
+        Some(bb)
+    }
+
+    /// Returns the IDs of all BBs which were (mutably) accessed so far.
+    #[inline]
+    pub fn touched(&self) -> impl Iterator<Item = I> + '_ {
+        self.orig.keys().copied()
+    }
+
+    /// Validates all touched BBs, and rolls back all edits on failure.
+    #[inline]
+    pub fn commit(mut self) -> Result<(), OffendingIds<I>> {
+        self.finish().map(|_| ())
+    }
+
+    /// Like [`ArenaEditor::commit`], but returns the original state
+    /// of all touched BBs on success.
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    pub(super) fn commit_with_orig(mut self) -> Result<Map<I, ABB<S, C, I>>, OffendingIds<I>> {
+        self.finish()
+    }
+
+    /// Discards all edits.
+    pub fn rollback(mut self) {
+        for (n, bb) in take(&mut self.orig) {
+            self.arena.bbs.insert(n, bb);
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn finish(&mut self) -> Result<Map<I, ABB<S, C, I>>, OffendingIds<I>> {
+        let orig = take(&mut self.orig);
+        let arena = &mut *self.arena;
+        let mut errs = Vec::new();
+        for &n in orig.keys() {
+            let bb = &arena.bbs[&n];
+            arena.check_intern(n, bb, &mut errs);
+            if bb.inner.is_placeholder() && arena.labels_of_bb(n).next().is_none() {
+                errs.push((n, n));
+            }
+        }
+        if let Err(e) = check::check_finish(errs) {
+            for (n, bb) in orig {
+                arena.bbs.insert(n, bb);
+            }
+            return Err(e);
+        }
+        for (&n, bb) in orig.iter() {
+            preds::unlink_bb(&mut arena.preds, n, bb);
+            preds::link_bb(&mut arena.preds, n, &arena.bbs[&n]);
+        }
+        Ok(orig)
+    }
+}
+
+impl<S, C, I: Idx> Drop for ArenaEditor<'_, S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+impl<S, C, I: Idx> BbMut<'_, S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    #[inline(always)]
+    pub fn id(&self) -> I {
+        self.bbid
+    }
+
+    /// Validates the BB, and rolls back the edit on failure.
+    #[inline]
+    pub fn commit(self) -> Result<(), OffendingIds<I>> {
+        self.editor.commit()
+    }
+
+    /// Discards the edit.
+    #[inline]
+    pub fn rollback(self) {
+        self.editor.rollback()
+    }
+}
+
+impl<S, C, I: Idx> Deref for BbMut<'_, S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    type Target = ABB<S, C, I>;
+
+    #[inline]
+    fn deref(&self) -> &ABB<S, C, I> {
+        &self.editor.arena.bbs[&self.bbid]
+    }
+}
+
+impl<S, C, I: Idx> DerefMut for BbMut<'_, S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut ABB<S, C, I> {
+        // the BB was already recorded by Arena::bb_mut
+        self.editor.arena.bbs.get_mut(&self.bbid).unwrap()
+    }
+}
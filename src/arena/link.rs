@@ -0,0 +1,269 @@
+use super::*;
+use alloc::collections::BTreeSet;
+
+/// Location of a BB before linking: (index of the arena, ID inside of it).
+/// Index 0 is the arena [`Arena::link`] was called on, index `i + 1`
+/// refers to the `i`-th arena of `others`.
+pub type LinkLoc<I = BbId> = (usize, I);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum LinkError<I = BbId> {
+    /// A label is attached to multiple concrete BBs, or to a non-public
+    /// concrete BB and a placeholder.
+    #[cfg_attr(
+        feature = "std",
+        error("symbol {label:?} is defined multiple times at {bbs:?}")
+    )]
+    DuplicateSymbol { label: String, bbs: Vec<LinkLoc<I>> },
+
+    /// No public concrete BB was found for a non-extern placeholder.
+    #[cfg_attr(feature = "std", error("unresolved symbol(s) {labels:?} at {bbs:?}"))]
+    UnresolvedSymbol {
+        labels: Vec<String>,
+        bbs: Vec<LinkLoc<I>>,
+    },
+}
+
+fn uf_find<I: Idx>(parents: &mut Map<LinkLoc<I>, LinkLoc<I>>, x: LinkLoc<I>) -> LinkLoc<I> {
+    let p = parents[&x];
+    if p == x {
+        x
+    } else {
+        let root = uf_find(parents, p);
+        parents.insert(x, root);
+        root
+    }
+}
+
+impl<S, C, I: Idx> Arena<S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I>,
+{
+    /// Merges all `others` into this arena and resolves placeholders.
+    ///
+    /// The BBs of this arena keep their IDs (except resolved placeholders),
+    /// all other BBs get renumbered. Every placeholder gets replaced by the
+    /// public concrete BB which carries one of its labels. Placeholders
+    /// sharing a label are merged. Unresolved placeholders are kept if they
+    /// are marked as `is_extern`, otherwise they are reported as errors.
+    ///
+    /// On success, returns the mapping (old ID -> new ID) of every
+    /// input arena, with index 0 describing this arena. Labels which
+    /// don't point to any BB (e.g. after [`Arena::bbs_mut`]) are dropped.
+    /// On failure, this arena is left untouched,
+    /// and `others` is returned together with the errors.
+    #[allow(clippy::type_complexity)]
+    pub fn link<A>(&mut self, others: A) -> Result<Vec<Map<I, I>>, (Vec<Self>, Vec<LinkError<I>>)>
+    where
+        A: IntoIterator<Item = Self>,
+    {
+        let mut others: Vec<Self> = others.into_iter().collect();
+        let mut errs = Vec::new();
+
+        // collect all symbols
+        let mut symbols: Map<&str, Vec<LinkLoc<I>>> = Map::new();
+        {
+            let all = core::iter::once(&*self).chain(others.iter());
+            for (ai, arena) in all.enumerate() {
+                for (label, &bbid) in arena.labels.iter() {
+                    if arena.bbs.contains_key(&bbid) {
+                        symbols.entry(label.as_str()).or_default().push((ai, bbid));
+                    }
+                }
+            }
+        }
+        let get_bb = |(ai, bbid): LinkLoc<I>| {
+            if ai == 0 {
+                &self.bbs[&bbid]
+            } else {
+                &others[ai - 1].bbs[&bbid]
+            }
+        };
+
+        // group placeholders which share a label
+        let mut parents: Map<LinkLoc<I>, LinkLoc<I>> = Map::new();
+        let mut ph_labels: Map<LinkLoc<I>, Vec<&str>> = Map::new();
+        for (&label, locs) in symbols.iter() {
+            let mut phs = locs.iter().filter(|&&l| get_bb(l).inner.is_placeholder());
+            if let Some(&first) = phs.next() {
+                parents.entry(first).or_insert(first);
+                ph_labels.entry(first).or_default().push(label);
+                for &i in phs {
+                    parents.entry(i).or_insert(i);
+                    ph_labels.entry(i).or_default().push(label);
+                    let (ra, rb) = (uf_find(&mut parents, first), uf_find(&mut parents, i));
+                    if ra != rb {
+                        parents.insert(core::cmp::max(ra, rb), core::cmp::min(ra, rb));
+                    }
+                }
+            }
+        }
+        let mut groups: Map<LinkLoc<I>, BTreeSet<LinkLoc<I>>> = Map::new();
+        let members: Vec<LinkLoc<I>> = parents.keys().copied().collect();
+        for i in members {
+            let root = uf_find(&mut parents, i);
+            groups.entry(root).or_default().insert(i);
+        }
+
+        // check for conflicting definitions
+        let mut defs: Map<&str, LinkLoc<I>> = Map::new();
+        let mut dups: BTreeSet<&str> = BTreeSet::new();
+        for (&label, locs) in symbols.iter() {
+            let concrete: Vec<LinkLoc<I>> = locs
+                .iter()
+                .copied()
+                .filter(|&l| get_bb(l).inner.is_concrete())
+                .collect();
+            let has_ph = concrete.len() != locs.len();
+            match concrete.as_slice() {
+                [] => {}
+                [c] if !has_ph || get_bb(*c).is_public => {
+                    defs.insert(label, *c);
+                }
+                _ => {
+                    dups.insert(label);
+                    errs.push(LinkError::DuplicateSymbol {
+                        label: label.into(),
+                        bbs: locs.clone(),
+                    });
+                }
+            }
+        }
+
+        // resolve the placeholder groups
+        let mut repl: Map<LinkLoc<I>, LinkLoc<I>> = Map::new();
+        for (&root, members) in groups.iter() {
+            let labels: BTreeSet<&str> = members
+                .iter()
+                .flat_map(|i| ph_labels[i].iter().copied())
+                .collect();
+            let targets: BTreeSet<LinkLoc<I>> =
+                labels.iter().filter_map(|l| defs.get(l).copied()).collect();
+            let target = match targets.len() {
+                0 => {
+                    let is_extern = members.iter().all(|&l| {
+                        matches!(
+                            get_bb(l).inner,
+                            BasicBlockInner::Placeholder { is_extern: true }
+                        )
+                    });
+                    if !is_extern && labels.is_disjoint(&dups) {
+                        errs.push(LinkError::UnresolvedSymbol {
+                            labels: labels.iter().map(|&l| l.into()).collect(),
+                            bbs: members.iter().copied().collect(),
+                        });
+                    }
+                    root
+                }
+                1 => *targets.iter().next().unwrap(),
+                _ => {
+                    errs.push(LinkError::DuplicateSymbol {
+                        label: labels.iter().next().copied().unwrap_or_default().into(),
+                        bbs: targets.into_iter().collect(),
+                    });
+                    continue;
+                }
+            };
+            for &i in members.iter() {
+                if i != target {
+                    repl.insert(i, target);
+                }
+            }
+        }
+        drop((symbols, ph_labels));
+        if !errs.is_empty() {
+            return Err((others, errs));
+        }
+
+        // allocate new IDs
+        let mut maps: Vec<Map<I, I>> = Vec::with_capacity(others.len() + 1);
+        maps.push(
+            self.bbs
+                .keys()
+                .filter(|&&i| !repl.contains_key(&(0, i)))
+                .map(|&i| (i, i))
+                .collect(),
+        );
+        let mut next_id = self
+            .bbs
+            .keys()
+            .next_back()
+            .map(|&i| i.index() + 1)
+            .unwrap_or(0);
+        for (ai, arena) in others.iter().enumerate() {
+            maps.push(
+                arena
+                    .bbs
+                    .keys()
+                    .filter(|&&i| !repl.contains_key(&(ai + 1, i)))
+                    .map(|&i| {
+                        next_id += 1;
+                        (i, I::from_index(next_id - 1))
+                    })
+                    .collect(),
+            );
+        }
+        for (&(ai, bbid), &(tai, tbbid)) in repl.iter() {
+            let t = maps[tai][&tbbid];
+            maps[ai].insert(bbid, t);
+        }
+
+        // move all BBs and rewrite the jump targets
+        for &(ai, bbid) in repl.keys() {
+            if ai == 0 {
+                self.bbs.remove(&bbid);
+                self.free_slot(bbid);
+            }
+        }
+        if repl.keys().any(|&(ai, _)| ai == 0) {
+            for i in self.bbs.values_mut() {
+                i.foreach_target_mut(|t| {
+                    if let Some(&nt) = maps[0].get(t) {
+                        *t = nt;
+                    }
+                });
+            }
+        }
+        for (ai, arena) in others.iter_mut().enumerate() {
+            let map = &maps[ai + 1];
+            for (bbid, mut bb) in take(&mut arena.bbs) {
+                if repl.contains_key(&(ai + 1, bbid)) {
+                    continue;
+                }
+                bb.foreach_target_mut(|t| {
+                    if let Some(&nt) = map.get(t) {
+                        *t = nt;
+                    }
+                });
+                self.bbs.insert(map[&bbid], bb);
+            }
+            for (bbid, w) in take(&mut arena.weights) {
+                if !repl.contains_key(&(ai + 1, bbid)) {
+                    self.weights.insert(map[&bbid], w);
+                }
+            }
+        }
+
+        // merge the labels
+        self.labels.retain(|_, i| match maps[0].get(i) {
+            Some(&nid) => {
+                *i = nid;
+                true
+            }
+            None => false,
+        });
+        for (ai, arena) in others.into_iter().enumerate() {
+            let map = &maps[ai + 1];
+            for (label, bbid) in arena.labels {
+                if let Some(&nid) = map.get(&bbid) {
+                    self.labels.insert(label, nid);
+                }
+            }
+        }
+
+        self.rebuild_preds();
+        Ok(maps)
+    }
+}
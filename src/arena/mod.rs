@@ -1,4 +1,5 @@
 use crate::bb::{BasicBlock, BasicBlockInner};
+use crate::id::{GenId, Idx};
 use crate::jump::ForeachTarget;
 use crate::{BbId, Label};
 use alloc::collections::{btree_map::Entry as MapEntry, BTreeMap as Map};
@@ -8,59 +9,132 @@ use core::mem::{replace, take};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+mod builder;
 mod check;
+mod compact;
+#[cfg(feature = "dot")]
+mod dot;
+mod edit;
+mod link;
 mod optimize;
+mod preds;
+mod undo;
+mod weights;
 
-type ABB<S, C> = BasicBlock<S, C, BbId>;
-type LabelMap = Map<String, BbId>;
+pub use builder::{ArenaBuilder, BuildError};
+pub use compact::CompactOrder;
+#[cfg(feature = "dot")]
+pub use dot::{DebugFormatter, DotFormatter};
+pub use edit::{ArenaEditor, BbMut};
+pub use link::{LinkError, LinkLoc};
+pub use optimize::OptimizeReport;
+pub use preds::Predecessors;
+pub use undo::{Checkpoint, Transaction, UndoLog};
+pub use weights::BranchWeights;
 
+#[allow(clippy::upper_case_acronyms)]
+type ABB<S, C, I = BbId> = BasicBlock<S, C, I>;
+type LabelMap<I = BbId> = Map<String, I>;
+
+/// A collection of BBs, which are referenced via IDs of type `I`
+/// (see [`Idx`](crate::id::Idx) for typed alternatives to [`BbId`]).
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-pub struct Arena<S, C> {
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        deserialize = "S: Deserialize<'de>, C: Deserialize<'de>, I: Deserialize<'de> + Ord"
+    ))
+)]
+pub struct Arena<S, C, I = BbId> {
     // invariant: every pointer to another BB should be valid inside the arena.
-    bbs: Map<BbId, ABB<S, C>>,
-    labels: LabelMap,
+    bbs: Map<I, ABB<S, C, I>>,
+    labels: LabelMap<I>,
+
+    // reverse index of `bbs` and `labels`, rebuilt on demand if `!preds_valid`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    preds: preds::PredMap<I>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    preds_valid: bool,
 
     // cache earliest insert point, used to speed up 'push' calls.
     #[cfg_attr(feature = "serde", serde(skip))]
     cache_ins_start: usize,
+
+    // generation of each slot which was freed at least once (others are 0)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Map::is_empty")
+    )]
+    gens: Map<I, u32>,
+
+    // profile data, see `Arena::set_branch_weights`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Map::is_empty")
+    )]
+    weights: Map<I, BranchWeights>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(thiserror::Error))]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-pub enum SetBbLabelError {
-    #[cfg_attr(feature = "std", error("got invalid basic block id {0}"))]
-    InvalidId(BbId),
+pub enum SetBbLabelError<I = BbId> {
+    #[cfg_attr(feature = "std", error("got invalid basic block id {0:?}"))]
+    InvalidId(I),
 
     #[cfg_attr(
         feature = "std",
-        error("label already exists with target = {orig_target}")
+        error("label already exists with target = {orig_target:?}")
     )]
-    LabelAlreadyExists { orig_target: BbId },
+    LabelAlreadyExists { orig_target: I },
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct OffendingIds<I = BbId>(pub Vec<(I, I)>);
+
+// thiserror can't infer the `I: Debug` bound through the `Vec`
+#[cfg(feature = "std")]
+impl<I: core::fmt::Debug> core::fmt::Display for OffendingIds<I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "got offending basic block ids (from -> to) {:?}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<I: core::fmt::Debug> std::error::Error for OffendingIds<I> {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(thiserror::Error))]
-#[cfg_attr(
-    feature = "std",
-    error("got offending basic block ids (from -> to) {0:?}")
-)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-pub struct OffendingIds(pub Vec<(BbId, BbId)>);
+pub enum FillError<I = BbId> {
+    #[cfg_attr(feature = "std", error("got invalid basic block id {0:?}"))]
+    InvalidId(I),
 
-impl<S, C> Default for Arena<S, C> {
+    #[cfg_attr(feature = "std", error("basic block {0:?} is not a placeholder"))]
+    NotPlaceholder(I),
+
+    #[cfg_attr(feature = "std", error("{0}"))]
+    Offending(OffendingIds<I>),
+}
+
+impl<S, C, I> Default for Arena<S, C, I> {
     #[inline]
     fn default() -> Self {
         Self {
             bbs: Map::new(),
             labels: Map::new(),
+            preds: Map::new(),
+            preds_valid: true,
             cache_ins_start: 0,
+            gens: Map::new(),
+            weights: Map::new(),
         }
     }
 }
 
-fn labels_of_bb(labels: &LabelMap, bbid: BbId) -> impl Iterator<Item = &str> {
+fn labels_of_bb<I: Idx>(labels: &LabelMap<I>, bbid: I) -> impl Iterator<Item = &str> {
     labels.iter().filter_map(move |(label, &curid)| {
         if curid == bbid {
             Some(label.as_str())
@@ -70,12 +144,12 @@ fn labels_of_bb(labels: &LabelMap, bbid: BbId) -> impl Iterator<Item = &str> {
     })
 }
 
-fn set_label(
-    labels: &mut LabelMap,
+fn set_label<I: Idx>(
+    labels: &mut LabelMap<I>,
     label: String,
-    target: BbId,
+    target: I,
     overwrite: bool,
-) -> Result<Option<BbId>, SetBbLabelError> {
+) -> Result<Option<I>, SetBbLabelError<I>> {
     match labels.entry(label) {
         MapEntry::Occupied(mut e) if overwrite => Ok(Some(replace(e.get_mut(), target))),
         MapEntry::Occupied(e) => Err(SetBbLabelError::LabelAlreadyExists {
@@ -88,7 +162,7 @@ fn set_label(
     }
 }
 
-impl<S, C> Arena<S, C> {
+impl<S, C, I: Idx> Arena<S, C, I> {
     #[inline(always)]
     pub fn new() -> Self {
         Default::default()
@@ -105,26 +179,100 @@ impl<S, C> Arena<S, C> {
     }
 
     #[inline(always)]
-    pub fn bbs(&self) -> &Map<BbId, ABB<S, C>> {
+    pub fn bbs(&self) -> &Map<I, ABB<S, C, I>> {
         &self.bbs
     }
 
+    /// Raw mutable access to the BBs. This invalidates the predecessor index,
+    /// call [`Arena::check`] (and [`Arena::update_predecessors`])
+    /// after any modifications.
+    /// BBs removed this way don't invalidate their [`GenId`]s.
+    /// Prefer [`Arena::edit`] or [`Arena::bb_mut`] for in-place rewrites.
     #[inline(always)]
-    pub fn bbs_mut(&mut self) -> &mut Map<BbId, ABB<S, C>> {
+    pub fn bbs_mut(&mut self) -> &mut Map<I, ABB<S, C, I>> {
+        self.preds_valid = false;
         &mut self.bbs
     }
 
     #[inline(always)]
-    pub fn labels(&self) -> &LabelMap {
+    pub fn labels(&self) -> &LabelMap<I> {
         &self.labels
     }
 
-    #[inline(always)]
-    pub fn labels_of_bb(&self, bbid: BbId) -> impl Iterator<Item = &str> {
-        labels_of_bb(&self.labels, bbid)
+    /// Returns all labels which point to the given BB (sorted).
+    /// This uses the predecessor index if it is up-to-date,
+    /// and falls back to scanning all labels otherwise.
+    pub fn labels_of_bb(&self, bbid: I) -> impl Iterator<Item = &str> {
+        let (index, scan) = if self.preds_valid {
+            (self.preds.get(&bbid), None)
+        } else {
+            (None, Some(&self.labels))
+        };
+        index
+            .into_iter()
+            .flat_map(|p| p.labels.iter().map(String::as_str))
+            .chain(
+                scan.into_iter()
+                    .flat_map(move |labels| labels_of_bb(labels, bbid)),
+            )
+    }
+
+    /// Returns all BBs and labels which reference the given BB.
+    ///
+    /// This index is maintained by all methods of the `Arena`, except
+    /// [`Arena::bbs_mut`]. It isn't serialized either, thus it is missing
+    /// after deserialization. In both cases, this returns `None` for every
+    /// BB until the index is rebuilt, either via [`Arena::update_predecessors`]
+    /// or implicitly by any method which needs it (e.g. [`Arena::remove`]).
+    #[inline]
+    pub fn predecessors(&self, bbid: I) -> Option<&Predecessors<I>> {
+        if self.preds_valid {
+            self.preds.get(&bbid)
+        } else {
+            None
+        }
     }
 
-    pub fn label2bb(&self, label: &str) -> Option<(BbId, &ABB<S, C>)> {
+    /// Returns the current generation of the given slot, which gets
+    /// incremented every time a BB is removed from it.
+    #[inline]
+    pub fn generation(&self, bbid: I) -> u32 {
+        self.gens.get(&bbid).copied().unwrap_or(0)
+    }
+
+    /// Returns a [`GenId`] for the given BB, if it exists,
+    /// which can be used to detect stale references later on.
+    #[inline]
+    pub fn gen_id(&self, bbid: I) -> Option<GenId<I>> {
+        if self.bbs.contains_key(&bbid) {
+            Some(GenId::new(bbid, self.generation(bbid)))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the referenced BB, or `None` if it was removed
+    /// (even if its slot was reused afterwards).
+    pub fn get(&self, id: GenId<I>) -> Option<&ABB<S, C, I>> {
+        if self.generation(id.id()) == id.generation() {
+            self.bbs.get(&id.id())
+        } else {
+            None
+        }
+    }
+
+    /// Marks the slot of a removed BB as free, invalidates its [`GenId`]s,
+    /// and drops its branch weights.
+    fn free_slot(&mut self, bbid: I) {
+        self.weights.remove(&bbid);
+        let gen = self.gens.entry(bbid).or_insert(0);
+        *gen = gen.wrapping_add(1);
+        if bbid.index() < self.cache_ins_start {
+            self.cache_ins_start = bbid.index();
+        }
+    }
+
+    pub fn label2bb(&self, label: &str) -> Option<(I, &ABB<S, C, I>)> {
         if let Some(bbid) = self.labels.get(label) {
             if let Some(bb) = self.bbs.get(bbid) {
                 return Some((*bbid, bb));
@@ -138,13 +286,55 @@ impl<S, C> Arena<S, C> {
     pub fn set_label(
         &mut self,
         label: Label,
-        target: BbId,
+        target: I,
         overwrite: bool,
-    ) -> Result<Option<BbId>, SetBbLabelError> {
-        if self.bbs.get(&target).is_none() {
+    ) -> Result<Option<I>, SetBbLabelError<I>> {
+        if !self.bbs.contains_key(&target) {
             return Err(SetBbLabelError::InvalidId(target));
         }
-        set_label(&mut self.labels, label.into_owned(), target, overwrite)
+        let label = label.into_owned();
+        let ret = set_label(&mut self.labels, label.clone(), target, overwrite)?;
+        if self.preds_valid {
+            if let Some(p) = ret.and_then(|orig| self.preds.get_mut(&orig)) {
+                p.labels.remove(&label);
+            }
+            if let Some(p) = self.preds.get_mut(&target) {
+                p.labels.insert(label);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Removes a label, and returns the BB it pointed to.
+    /// Note that placeholders without labels are rejected by [`Arena::check`].
+    pub fn remove_label(&mut self, label: &str) -> Option<I> {
+        let ret = self.labels.remove(label)?;
+        if self.preds_valid {
+            if let Some(p) = self.preds.get_mut(&ret) {
+                p.labels.remove(label);
+            }
+        }
+        Some(ret)
+    }
+
+    /// Renames the label $from to $to, which keeps pointing to the same BB.
+    /// Returns `None` if $from doesn't exist, otherwise the result is
+    /// the same as of [`Arena::set_label`] for $to.
+    pub fn rename_label(
+        &mut self,
+        from: &str,
+        to: Label,
+        overwrite: bool,
+    ) -> Option<Result<Option<I>, SetBbLabelError<I>>> {
+        let target = *self.labels.get(from)?;
+        if from == to {
+            return Some(Ok(None));
+        }
+        let ret = self.set_label(to, target, overwrite);
+        if ret.is_ok() {
+            self.remove_label(from);
+        }
+        Some(ret)
     }
 
     pub fn shrink_to_fit(&mut self) {
@@ -156,11 +346,11 @@ impl<S, C> Arena<S, C> {
     }
 }
 
-impl<S, C> ForeachTarget for Arena<S, C>
+impl<S, C, I> ForeachTarget for Arena<S, C, I>
 where
-    ABB<S, C>: ForeachTarget<JumpTarget = BbId>,
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I>,
 {
-    type JumpTarget = BbId;
+    type JumpTarget = I;
 
     fn foreach_target<F>(&self, mut f: F)
     where
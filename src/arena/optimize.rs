@@ -1,44 +1,105 @@
-use super::Arena;
+use super::{preds, Arena, ABB};
 use crate::bb::BasicBlockInner;
+use crate::id::Idx;
 use crate::jump::{self, ForeachTarget};
 use crate::BbId;
 use alloc::collections::{BTreeMap as Map, BTreeSet};
-use alloc::vec::Vec;
-use core::mem::{drop, take};
+use alloc::{string::String, vec::Vec};
+use core::mem::take;
 
-/// Temporary information describing the modifications to be done
-/// and cached data, per BbId.
-///
-/// This structure is at least implicitly wrapped in an `Option` (key exists?),
-/// if Some, then TR $key->$target,
-/// otherwise, remove every occurence of $key if possible,
-/// panic otherwise (especially if otherwise invariants would be violated)
-///
-/// To avoid reallocations and multiple intermediate data types,
-/// this structure goes through multiple stages, which contain
-/// similiar, but not equivalent sets of data.
-#[derive(Clone)]
-struct TransInfo {
-    /// for $key->$target search-and-replace; default = $key
-    target: usize,
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-    /// references from $i pointing to $key,
-    /// useful for optimizations which rely on the fact that only
-    /// one other BB depends on a BB, then they're mergable
-    /// default = 1
-    refs: BTreeSet<usize>,
+/// Describes which BBs and labels were changed by [`Arena::optimize`]
+/// or a [`PassManager`](crate::pass::PassManager).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "I: Deserialize<'de> + Ord"))
+)]
+pub struct OptimizeReport<I = BbId> {
+    /// BBs which were removed because they were unreachable
+    pub removed: BTreeSet<I>,
+
+    /// BBs which were merged into their predecessor (tail -> head)
+    pub merged: Map<I, I>,
+
+    /// empty BBs which all references got redirected from (from -> target)
+    pub forwarded: Map<I, I>,
+
+    /// labels which were removed together with their BB
+    pub dropped_labels: BTreeSet<String>,
+
+    /// BBs whose switch got replaced by an unconditional jump
+    pub collapsed: BTreeSet<I>,
 }
 
-impl TransInfo {
+impl<I> Default for OptimizeReport<I> {
     #[inline]
-    fn new(id: usize) -> Self {
+    fn default() -> Self {
         Self {
-            target: id,
-            refs: BTreeSet::new(),
+            removed: BTreeSet::new(),
+            merged: Map::new(),
+            forwarded: Map::new(),
+            dropped_labels: BTreeSet::new(),
+            collapsed: BTreeSet::new(),
         }
     }
 }
 
+impl<I: Idx> OptimizeReport<I> {
+    #[inline]
+    pub fn is_modified(&self) -> bool {
+        !(self.removed.is_empty()
+            && self.merged.is_empty()
+            && self.forwarded.is_empty()
+            && self.dropped_labels.is_empty()
+            && self.collapsed.is_empty())
+    }
+
+    /// Returns the ID of the BB which now contains the content of
+    /// (or is the jump target instead of) the given BB,
+    /// or `None` if it was removed.
+    pub fn remap(&self, mut bbid: I) -> Option<I> {
+        loop {
+            if let Some(&head) = self.merged.get(&bbid) {
+                bbid = head;
+            } else if let Some(&target) = self.forwarded.get(&bbid) {
+                bbid = target;
+            } else if self.removed.contains(&bbid) {
+                return None;
+            } else {
+                return Some(bbid);
+            }
+        }
+    }
+
+    /// Applies [`OptimizeReport::remap`] to the keys of an external map.
+    /// Entries of removed BBs are dropped, and if multiple entries end up
+    /// with the same key, they are combined using `combine(existing, new)`,
+    /// where `existing` belongs to the BB with the lowest original ID.
+    pub fn remap_map<V, F>(&self, map: Map<I, V>, mut combine: F) -> Map<I, V>
+    where
+        F: FnMut(&mut V, V),
+    {
+        let mut ret = Map::new();
+        for (k, v) in map {
+            if let Some(nk) = self.remap(k) {
+                match ret.entry(nk) {
+                    alloc::collections::btree_map::Entry::Vacant(e) => {
+                        e.insert(v);
+                    }
+                    alloc::collections::btree_map::Entry::Occupied(mut e) => {
+                        combine(e.get_mut(), v);
+                    }
+                }
+            }
+        }
+        ret
+    }
+}
+
 /// check function which makes sure that no reference to $exclude exists
 /// inside of $container.
 fn fetchk<C>(is_mergable: &mut bool, container: &C, exclude: <C as ForeachTarget>::JumpTarget)
@@ -53,90 +114,220 @@ where
     });
 }
 
-impl<S, C> Arena<S, C>
+/// If the BB is a non-public, empty BB which only jumps to another BB,
+/// returns the jump target. Its unwind target is irrelevant, because
+/// there are no statements which could unwind.
+fn forward_target<S, C, I: Idx>(bbid: I, bb: &ABB<S, C, I>) -> Option<I> {
+    if bb.is_public {
+        return None;
+    }
+    match &bb.inner {
+        BasicBlockInner::Concrete {
+            statements,
+            condjmp: None,
+            next: jump::Unconditional::Jump(trg),
+            ..
+        } if statements.is_empty() && *trg != bbid => Some(*trg),
+        _ => None,
+    }
+}
+
+/// Returns true if the BB ends with an indirect jump without
+/// declared targets, which might jump to any labeled BB.
+fn jumps_to_labels<S, C, I>(bb: &ABB<S, C, I>) -> bool {
+    matches!(
+        &bb.inner,
+        BasicBlockInner::Concrete {
+            next: jump::Unconditional::Indirect(None),
+            ..
+        }
+    )
+}
+
+impl<S, C, I: Idx> Arena<S, C, I>
 where
-    S: ForeachTarget<JumpTarget = BbId>,
-    C: ForeachTarget<JumpTarget = BbId>,
+    S: ForeachTarget<JumpTarget = I>,
+    C: ForeachTarget<JumpTarget = I>,
 {
-    pub fn optimize(&mut self) -> bool {
-        let mut new_in_use = Vec::with_capacity(self.bbs.len());
-        let mut trm: Map<BbId, Option<TransInfo>> = Map::new();
-
-        for (&from, i) in self.bbs.iter() {
-            if i.is_public {
-                new_in_use.push(from);
-            } else if let BasicBlockInner::Concrete {
-                statements,
-                condjmp,
-                next,
-            } = &i.inner
-            {
-                if statements.is_empty() && condjmp.is_none() {
-                    if let jump::Unconditional::Jump(trg) = *next {
-                        if from != trg {
-                            trm.entry(from)
-                                .or_insert_with(|| Some(TransInfo::new(from)))
-                                .as_mut()
-                                .unwrap()
-                                .target = trg;
-                        }
-                    }
-                }
+    /// Runs the standard optimization passes (see [`PassManager::standard`])
+    /// until nothing changes anymore.
+    ///
+    /// [`PassManager::standard`]: crate::pass::PassManager::standard
+    pub fn optimize(&mut self) -> OptimizeReport<I> {
+        let mut report = OptimizeReport::default();
+        loop {
+            let mut modified = self.remove_unreachable(&mut report);
+            modified |= self.collapse_switches(&mut report);
+            modified |= self.forward_jumps(&mut report);
+            modified |= self.merge_chains(&mut report);
+            if !modified {
+                break report;
             }
         }
+    }
 
-        // recursively mark anything as in-use only if reachable from in-use or pub
+    /// Removes all BBs which aren't reachable from any public BB,
+    /// together with all labels which point to them.
+    /// Labeled BBs are kept if any reachable BB ends with `Indirect(None)`.
+    pub(crate) fn remove_unreachable(&mut self, report: &mut OptimizeReport<I>) -> bool {
+        // recursively mark anything as in-use only if reachable from pub
         let mut in_use = BTreeSet::new();
-        while !new_in_use.is_empty() {
-            for i in take(&mut new_in_use) {
-                if let Some(ent) = self.bbs.get(&i) {
-                    if in_use.insert(i) {
-                        // really new entry
-                        ent.foreach_target(|&trg| {
-                            trm.entry(trg)
-                                .or_insert_with(|| Some(TransInfo::new(trg)))
-                                .as_mut()
-                                .unwrap()
-                                .refs
-                                .insert(i);
-                            new_in_use.push(trg);
-                        });
+        let mut labels_in_use = false;
+        let mut new_in_use: Vec<_> = self
+            .bbs
+            .iter()
+            .filter(|(_, i)| i.is_public)
+            .map(|(&n, _)| n)
+            .collect();
+        while let Some(i) = new_in_use.pop() {
+            if let Some(ent) = self.bbs.get(&i) {
+                if in_use.insert(i) {
+                    // really new entry
+                    ent.foreach_target(|&trg| new_in_use.push(trg));
+                    if !labels_in_use && jumps_to_labels(ent) {
+                        labels_in_use = true;
+                        new_in_use.extend(self.labels.values().copied());
                     }
                 }
             }
         }
-        drop(new_in_use);
+        if in_use.len() == self.bbs.len() {
+            return false;
+        }
+
+        let unused: Vec<I> = self
+            .bbs
+            .keys()
+            .filter(|i| !in_use.contains(i))
+            .copied()
+            .collect();
+        for i in unused {
+            self.bbs.remove(&i);
+            self.free_slot(i);
+            report.removed.insert(i);
+        }
+        let bbs = &self.bbs;
+        self.labels.retain(|label, bbid| {
+            let keep = bbs.contains_key(bbid);
+            if !keep {
+                report.dropped_labels.insert(label.clone());
+            }
+            keep
+        });
+        self.rebuild_preds();
+        true
+    }
 
-        // check all references for one-refs (which may be mergable)
-        for (n, ti) in trm
+    /// Redirects all references to non-public, empty BBs which
+    /// only jump to another BB to their final jump target.
+    /// The skipped BBs become unreachable.
+    pub(crate) fn forward_jumps(&mut self, report: &mut OptimizeReport<I>) -> bool {
+        let trm: Map<I, I> = self
+            .bbs
             .iter()
-            .filter_map(|(&n, ti)| ti.as_ref().map(|ti2| (n, ti2)))
-        {
-            if ti.refs.len() != 1 {
-                continue;
+            .filter_map(|(&n, i)| forward_target(n, i).map(|t| (n, t)))
+            .collect();
+        if trm.is_empty() {
+            return false;
+        }
+
+        // resolve chains of forwarded BBs, skip cycles
+        let resolve = |mut t: I| {
+            let mut seen = BTreeSet::new();
+            while let Some(&nt) = trm.get(&t) {
+                if !seen.insert(t) {
+                    return None;
+                }
+                t = nt;
             }
-            let bbheadref = *ti.refs.iter().next().unwrap();
-            if bbheadref == n {
-                continue;
+            Some(t)
+        };
+        let trm: Map<I, I> = trm
+            .keys()
+            .filter_map(|&n| resolve(n).map(|t| (n, t)))
+            .collect();
+
+        let mut modified = false;
+        let mut replace = |target: &mut I| {
+            if let Some(&t) = trm.get(target) {
+                if *target != t {
+                    *target = t;
+                    modified = true;
+                }
             }
-            if trm
-                .get(&bbheadref)
-                .and_then(Option::as_ref)
-                .map(|hti| hti.target != bbheadref)
-                == Some(true)
-            {
-                // head is already redirected, skip
+        };
+        for i in self.bbs.values_mut() {
+            i.foreach_target_mut(&mut replace);
+        }
+        for i in self.labels.values_mut() {
+            replace(i);
+        }
+        if modified {
+            report.forwarded.extend(trm);
+            self.rebuild_preds();
+        }
+        modified
+    }
+
+    /// Replaces every switch whose cases all jump to the default target
+    /// by an unconditional jump.
+    pub(crate) fn collapse_switches(&mut self, report: &mut OptimizeReport<I>) -> bool {
+        let mut modified = false;
+        for (&n, bb) in self.bbs.iter_mut() {
+            if let BasicBlockInner::Concrete { next, .. } = &mut bb.inner {
+                let target = match next {
+                    jump::Unconditional::Switch(sw) => sw.single_target().copied(),
+                    _ => None,
+                };
+                if let Some(target) = target {
+                    // the set of jump targets stays the same
+                    *next = jump::Unconditional::Jump(target);
+                    if let Some(w) = self.weights.get_mut(&n) {
+                        w.switch = None;
+                        if w.is_empty() {
+                            self.weights.remove(&n);
+                        }
+                    }
+                    report.collapsed.insert(n);
+                    modified = true;
+                }
+            }
+        }
+        modified
+    }
+
+    /// Merges every non-public BB which is only referenced by a single
+    /// other BB, which unconditionally jumps to it, into that other BB.
+    /// Labeled BBs aren't merged if any BB ends with `Indirect(None)`,
+    /// and BBs with differing unwind targets are never merged.
+    pub(crate) fn merge_chains(&mut self, report: &mut OptimizeReport<I>) -> bool {
+        self.ensure_preds();
+        let mut modified = false;
+        let labels_in_use = self.bbs.values().any(jumps_to_labels);
+        let candidates: Vec<I> = self.bbs.keys().copied().collect();
+
+        for n in candidates {
+            let bbheadref = match self.preds.get(&n) {
+                Some(p) if p.bbs.len() == 1 && (!labels_in_use || p.labels.is_empty()) => {
+                    *p.bbs.iter().next().unwrap()
+                }
+                _ => continue,
+            };
+            if bbheadref == n {
                 continue;
             }
             let mut is_mergable = false;
-            if let Some(bbhead) = self.bbs.get_mut(&bbheadref) {
+            let mut h_unwind = None;
+            if let Some(bbhead) = self.bbs.get(&bbheadref) {
                 if let BasicBlockInner::Concrete {
                     statements,
                     condjmp,
                     next,
-                } = &mut bbhead.inner
+                    unwind,
+                } = &bbhead.inner
                 {
                     is_mergable = condjmp.is_none() && *next == jump::Unconditional::Jump(n);
+                    h_unwind = *unwind;
 
                     // make sure that we don't have any additional references to bbtail
                     fetchk(&mut is_mergable, statements, n);
@@ -146,8 +337,14 @@ where
                 continue;
             }
             let bbtail = if let Some(bbtail) = self.bbs.get_mut(&n) {
-                if bbtail.is_public || !bbtail.inner.is_concrete() {
-                    continue;
+                match &bbtail.inner {
+                    // the statements of both BBs must unwind to the same landing pad
+                    BasicBlockInner::Concrete { unwind, .. } if !bbtail.is_public => {
+                        if *unwind != h_unwind {
+                            continue;
+                        }
+                    }
+                    _ => continue,
                 }
                 fetchk(&mut is_mergable, bbtail, n);
                 if !is_mergable {
@@ -159,101 +356,66 @@ where
             };
 
             // mergable
+            let tail_labels = self.preds.remove(&n).unwrap().labels;
+            let tail_weights = self.weights.remove(&n);
+            self.bbs.remove(&n);
+            self.free_slot(n);
             if let BasicBlockInner::Concrete {
                 mut statements,
                 condjmp,
                 next,
+                ..
             } = bbtail
             {
+                let bbhead = self.bbs.get_mut(&bbheadref).unwrap();
                 if let BasicBlockInner::Concrete {
                     statements: ref mut h_statements,
                     condjmp: ref mut h_condjmp,
                     next: ref mut h_next,
-                } = &mut self.bbs.get_mut(&bbheadref).unwrap().inner
+                    ..
+                } = &mut bbhead.inner
                 {
-                    in_use.remove(&n);
-                    if h_statements.is_empty() {
+                    let keep_labels = h_statements.is_empty();
+                    h_statements.append(&mut statements);
+                    *h_condjmp = condjmp;
+                    *h_next = next;
+                    // the head now ends with the branches of the tail
+                    match tail_weights {
+                        Some(w) => self.weights.insert(bbheadref, w),
+                        None => self.weights.remove(&bbheadref),
+                    };
+
+                    // the references now originate from the head
+                    preds::unlink_bb(&mut self.preds, n, &*bbhead);
+                    preds::link_bb(&mut self.preds, bbheadref, &*bbhead);
+
+                    if keep_labels {
                         // this normally only happens if $head.is_public
                         // merge labels manually
-                        for ltrg in self.labels.values_mut() {
-                            if *ltrg == n {
-                                *ltrg = bbheadref;
-                            }
+                        for i in tail_labels.iter() {
+                            *self.labels.get_mut(i).unwrap() = bbheadref;
+                        }
+                        self.preds
+                            .get_mut(&bbheadref)
+                            .unwrap()
+                            .labels
+                            .extend(tail_labels);
+                    } else {
+                        for i in tail_labels.iter() {
+                            self.labels.remove(i);
                         }
+                        report.dropped_labels.extend(tail_labels);
                     }
-                    h_statements.append(&mut statements);
-                    *h_condjmp = condjmp;
-                    *h_next = next;
                 } else {
                     unreachable!();
                 }
             } else {
                 unreachable!();
             }
+            report.merged.insert(n, bbheadref);
+            modified = true;
         }
 
-        // apply in_use
-        let mut modified = false;
-        for i in self.bbs.keys() {
-            let e = trm.entry(*i);
-            if in_use.contains(i) {
-                if let alloc::collections::btree_map::Entry::Occupied(mut e) = e {
-                    if let Some(ti) = e.get_mut() {
-                        if *i != ti.target {
-                            modified = true;
-                        } else {
-                            e.remove_entry();
-                        }
-                    }
-                }
-            } else {
-                *e.or_default() = None;
-                modified = true;
-            }
-        }
-        if !modified {
-            // nothing left to do, we are done
-            return false;
-        }
-
-        // remove to-be-removed BBs
-        {
-            let mut it = trm.iter().filter(|x| x.1.is_none()).map(|x| x.0);
-            if let Some(&nfi) = it.next() {
-                if nfi < self.cache_ins_start {
-                    self.cache_ins_start = nfi;
-                }
-                self.bbs.remove(&nfi);
-                for n in it {
-                    self.bbs.remove(&n);
-                }
-            }
-        }
-
-        // replace jump targets
-        for i in self.bbs.values_mut() {
-            i.foreach_target_mut(|target| {
-                if let Some(Some(ti)) = trm.get(target) {
-                    *target = ti.target;
-                }
-            });
-        }
-
-        self.labels = take(&mut self.labels)
-            .into_iter()
-            .filter_map(|(label, bbid)| {
-                // remove all labels which point to a BBID which should be deleted,
-                // either because it is marked in trm -> None, or the BBID is invalid.
-                // update all remaining labels
-                match trm.get(&bbid) {
-                    Some(None) => None,
-                    Some(Some(ti)) => Some(ti.target),
-                    None => Some(bbid),
-                }
-                .map(|nn| (label, nn))
-            })
-            .collect();
-
-        true
+        modified
     }
 }
@@ -0,0 +1,92 @@
+use super::*;
+use alloc::collections::BTreeSet;
+
+/// Back-references to a single BB, maintained incrementally by the [`Arena`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "I: Deserialize<'de> + Ord"))
+)]
+pub struct Predecessors<I = BbId> {
+    /// all BBs which contain at least one jump target pointing to this BB
+    /// (including the BB itself, if it references itself)
+    pub bbs: BTreeSet<I>,
+
+    /// all labels which point to this BB
+    pub labels: BTreeSet<String>,
+}
+
+impl<I> Default for Predecessors<I> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            bbs: BTreeSet::new(),
+            labels: BTreeSet::new(),
+        }
+    }
+}
+
+pub(super) type PredMap<I = BbId> = Map<I, Predecessors<I>>;
+
+pub(super) fn link_bb<I: Idx, B>(preds: &mut PredMap<I>, from: I, bb: &B)
+where
+    B: ForeachTarget<JumpTarget = I>,
+{
+    bb.foreach_target(|t| {
+        if let Some(p) = preds.get_mut(t) {
+            p.bbs.insert(from);
+        }
+    });
+}
+
+pub(super) fn unlink_bb<I: Idx, B>(preds: &mut PredMap<I>, from: I, bb: &B)
+where
+    B: ForeachTarget<JumpTarget = I>,
+{
+    bb.foreach_target(|t| {
+        if let Some(p) = preds.get_mut(t) {
+            p.bbs.remove(&from);
+        }
+    });
+}
+
+impl<S, C, I: Idx> Arena<S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I>,
+{
+    /// Rebuilds the predecessor index from scratch.
+    pub(crate) fn rebuild_preds(&mut self) {
+        let mut preds: PredMap<I> = self
+            .bbs
+            .keys()
+            .map(|&i| (i, Predecessors::default()))
+            .collect();
+        for (&n, i) in self.bbs.iter() {
+            link_bb(&mut preds, n, i);
+        }
+        for (label, t) in self.labels.iter() {
+            if let Some(p) = preds.get_mut(t) {
+                p.labels.insert(label.clone());
+            }
+        }
+        self.preds = preds;
+        self.preds_valid = true;
+    }
+
+    /// Rebuilds the predecessor index if it got invalidated via
+    /// [`Arena::bbs_mut`] or deserialization.
+    #[inline]
+    pub(super) fn ensure_preds(&mut self) {
+        if !self.preds_valid {
+            self.rebuild_preds();
+        }
+    }
+
+    /// Brings the predecessor index up-to-date again after modifications via
+    /// [`Arena::bbs_mut`] or after deserialization, see [`Arena::predecessors`].
+    #[inline]
+    pub fn update_predecessors(&mut self) {
+        self.ensure_preds();
+    }
+}
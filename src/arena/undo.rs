@@ -0,0 +1,350 @@
+use super::*;
+use core::ops::Deref;
+
+#[derive(Debug)]
+enum UndoOp<S, C, I> {
+    /// previous state of a BB (`None` if it didn't exist),
+    /// and the generation of its slot at that time
+    Bb(I, Option<ABB<S, C, I>>, u32),
+    /// previous target of a label (`None` if it didn't exist)
+    Label(String, Option<I>),
+    /// previous branch weights of a BB (`None` if there were none)
+    Weights(I, Option<BranchWeights>),
+    /// previous state of the whole arena, including the generations
+    /// of the slots at that time
+    Snapshot(
+        Map<I, ABB<S, C, I>>,
+        LabelMap<I>,
+        Map<I, BranchWeights>,
+        Map<I, u32>,
+    ),
+}
+
+/// A sequence of recorded modifications of an [`Arena`],
+/// which can be reverted via [`UndoLog::undo`].
+#[derive(Debug)]
+pub struct UndoLog<S, C, I = BbId> {
+    ops: Vec<UndoOp<S, C, I>>,
+}
+
+/// A position inside of a [`Transaction`], see [`Transaction::checkpoint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Checkpoint(usize);
+
+/// Records all modifications of an [`Arena`] done through it,
+/// which allows rolling back to any earlier [`Checkpoint`].
+///
+/// Dropping a transaction without calling [`Transaction::commit`]
+/// rolls back all of its modifications.
+pub struct Transaction<'a, S, C, I = BbId>
+where
+    I: Idx,
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    arena: &'a mut Arena<S, C, I>,
+    log: UndoLog<S, C, I>,
+}
+
+impl<S, C, I: Idx> Arena<S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    #[inline]
+    pub fn transaction(&mut self) -> Transaction<'_, S, C, I> {
+        Transaction {
+            arena: self,
+            log: UndoLog::default(),
+        }
+    }
+
+    fn apply_undo(&mut self, op: UndoOp<S, C, I>) -> UndoOp<S, C, I> {
+        match op {
+            UndoOp::Bb(n, Some(bb), gen) => {
+                // a restored BB is the same as before, thus references
+                // to it (e.g. `GenId`s) become valid again
+                let cur_gen = self.generation(n);
+                self.gens.insert(n, gen);
+                UndoOp::Bb(n, self.bbs.insert(n, bb), cur_gen)
+            }
+            UndoOp::Bb(n, None, _) => {
+                let cur_gen = self.generation(n);
+                let prev = self.bbs.remove(&n);
+                if prev.is_some() {
+                    self.free_slot(n);
+                }
+                UndoOp::Bb(n, prev, cur_gen)
+            }
+            UndoOp::Label(label, Some(t)) => {
+                let prev = self.labels.insert(label.clone(), t);
+                UndoOp::Label(label, prev)
+            }
+            UndoOp::Label(label, None) => {
+                let prev = self.labels.remove(&label);
+                UndoOp::Label(label, prev)
+            }
+            UndoOp::Weights(n, Some(w)) => UndoOp::Weights(n, self.weights.insert(n, w)),
+            UndoOp::Weights(n, None) => UndoOp::Weights(n, self.weights.remove(&n)),
+            UndoOp::Snapshot(bbs, labels, weights, gens) => {
+                // invalidate the BBs which disappear, and those whose slot
+                // got reused in the meantime. BBs which were only modified
+                // in-place keep their generation, like with `Arena::edit`.
+                let cur_gens = self.gens.clone();
+                let replaced: Vec<I> = self
+                    .bbs
+                    .keys()
+                    .copied()
+                    .filter(|n| {
+                        !bbs.contains_key(n)
+                            || gens.get(n).copied().unwrap_or(0) != self.generation(*n)
+                    })
+                    .collect();
+                for n in replaced {
+                    self.free_slot(n);
+                }
+                self.cache_ins_start = 0;
+                UndoOp::Snapshot(
+                    replace(&mut self.bbs, bbs),
+                    replace(&mut self.labels, labels),
+                    replace(&mut self.weights, weights),
+                    cur_gens,
+                )
+            }
+        }
+    }
+}
+
+impl<S, C, I> Default for UndoLog<S, C, I> {
+    #[inline]
+    fn default() -> Self {
+        Self { ops: Vec::new() }
+    }
+}
+
+impl<S, C, I: Idx> UndoLog<S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Reverts all recorded modifications, which must be the most recent
+    /// modifications of $arena. Returns the log which reverts this undo (redo).
+    pub fn undo(self, arena: &mut Arena<S, C, I>) -> UndoLog<S, C, I> {
+        let mut ret = Self::default();
+        ret.ops.reserve(self.ops.len());
+        for op in self.ops.into_iter().rev() {
+            ret.ops.push(arena.apply_undo(op));
+        }
+        arena.rebuild_preds();
+        ret
+    }
+}
+
+impl<S, C, I: Idx> Deref for Transaction<'_, S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    type Target = Arena<S, C, I>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Arena<S, C, I> {
+        self.arena
+    }
+}
+
+impl<S, C, I: Idx> Transaction<'_, S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    /// Returns the current position, which can be passed to
+    /// [`Transaction::rollback_to`] later. Checkpoints can be nested.
+    #[inline]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.log.ops.len())
+    }
+
+    /// Reverts all modifications done after $cp was created.
+    /// Any checkpoints created after $cp become invalid.
+    pub fn rollback_to(&mut self, cp: Checkpoint) {
+        if cp.0 >= self.log.ops.len() {
+            return;
+        }
+        for op in self.log.ops.drain(cp.0..).rev() {
+            self.arena.apply_undo(op);
+        }
+        self.arena.rebuild_preds();
+    }
+
+    /// Reverts all modifications done through this transaction.
+    #[inline]
+    pub fn rollback(mut self) {
+        self.rollback_to(Checkpoint(0));
+    }
+
+    /// Keeps all modifications, and returns the log to undo them.
+    #[inline]
+    pub fn commit(mut self) -> UndoLog<S, C, I> {
+        take(&mut self.log)
+    }
+
+    /// See [`Arena::push`].
+    #[allow(clippy::type_complexity)]
+    pub fn push(&mut self, bb: ABB<S, C, I>) -> Result<I, (ABB<S, C, I>, OffendingIds<I>)> {
+        let ret = self.arena.push(bb)?;
+        let gen = self.arena.generation(ret);
+        self.log.ops.push(UndoOp::Bb(ret, None, gen));
+        Ok(ret)
+    }
+
+    /// See [`Arena::reserve`].
+    pub fn reserve(&mut self) -> I {
+        let ret = self.arena.reserve();
+        let gen = self.arena.generation(ret);
+        self.log.ops.push(UndoOp::Bb(ret, None, gen));
+        ret
+    }
+
+    /// See [`Arena::fill`].
+    #[allow(clippy::type_complexity)]
+    pub fn fill(&mut self, bbid: I, bb: ABB<S, C, I>) -> Result<(), (ABB<S, C, I>, FillError<I>)> {
+        let prev = self.arena.bbs.get(&bbid).cloned();
+        self.arena.fill(bbid, bb)?;
+        let gen = self.arena.generation(bbid);
+        self.log.ops.push(UndoOp::Bb(bbid, prev, gen));
+        Ok(())
+    }
+
+    /// See [`Arena::remove`].
+    #[allow(clippy::type_complexity)]
+    pub fn remove(
+        &mut self,
+        bbid: I,
+    ) -> Option<Result<(ABB<S, C, I>, Vec<String>), OffendingIds<I>>> {
+        let weights = self.arena.weights.get(&bbid).cloned();
+        let gen = self.arena.generation(bbid);
+        let ret = self.arena.remove(bbid)?;
+        if let Ok((bb, labels)) = &ret {
+            if weights.is_some() {
+                self.log.ops.push(UndoOp::Weights(bbid, weights));
+            }
+            self.log.ops.push(UndoOp::Bb(bbid, Some(bb.clone()), gen));
+            self.log.ops.extend(
+                labels
+                    .iter()
+                    .map(|label| UndoOp::Label(label.clone(), Some(bbid))),
+            );
+        }
+        Some(ret)
+    }
+
+    /// See [`Arena::set_label`].
+    pub fn set_label(
+        &mut self,
+        label: Label,
+        target: I,
+        overwrite: bool,
+    ) -> Result<Option<I>, SetBbLabelError<I>> {
+        let ret = self.arena.set_label(label.clone(), target, overwrite)?;
+        self.log.ops.push(UndoOp::Label(label.into_owned(), ret));
+        Ok(ret)
+    }
+
+    /// See [`Arena::remove_label`].
+    pub fn remove_label(&mut self, label: &str) -> Option<I> {
+        let ret = self.arena.remove_label(label)?;
+        self.log.ops.push(UndoOp::Label(label.into(), Some(ret)));
+        Some(ret)
+    }
+
+    /// See [`Arena::rename_label`].
+    pub fn rename_label(
+        &mut self,
+        from: &str,
+        to: Label,
+        overwrite: bool,
+    ) -> Option<Result<Option<I>, SetBbLabelError<I>>> {
+        let target = *self.arena.labels.get(from)?;
+        let ret = self.arena.rename_label(from, to.clone(), overwrite)?;
+        if let Ok(prev) = ret {
+            if from != to {
+                self.log.ops.push(UndoOp::Label(to.into_owned(), prev));
+                self.log.ops.push(UndoOp::Label(from.into(), Some(target)));
+            }
+        }
+        Some(ret)
+    }
+
+    /// See [`Arena::set_branch_weights`].
+    pub fn set_branch_weights(
+        &mut self,
+        bbid: I,
+        weights: BranchWeights,
+    ) -> Result<Option<BranchWeights>, BranchWeights> {
+        let ret = self.arena.set_branch_weights(bbid, weights)?;
+        self.log.ops.push(UndoOp::Weights(bbid, ret.clone()));
+        Ok(ret)
+    }
+
+    /// See [`Arena::remove_branch_weights`].
+    pub fn remove_branch_weights(&mut self, bbid: I) -> Option<BranchWeights> {
+        let ret = self.arena.remove_branch_weights(bbid)?;
+        self.log.ops.push(UndoOp::Weights(bbid, Some(ret.clone())));
+        Some(ret)
+    }
+
+    /// Runs $f on an [`ArenaEditor`] and commits it afterwards,
+    /// see [`ArenaEditor::commit`].
+    pub fn edit<F, R>(&mut self, f: F) -> Result<R, OffendingIds<I>>
+    where
+        F: FnOnce(&mut ArenaEditor<'_, S, C, I>) -> R,
+    {
+        let mut editor = self.arena.edit();
+        let ret = f(&mut editor);
+        let orig = editor.commit_with_orig()?;
+        // edits happen in-place, the generations stay the same
+        let arena = &*self.arena;
+        self.log.ops.extend(
+            orig.into_iter()
+                .map(|(n, bb)| UndoOp::Bb(n, Some(bb), arena.generation(n))),
+        );
+        Ok(ret)
+    }
+
+    /// Runs an arbitrary modification on the arena (e.g. [`Arena::link`]
+    /// or a [`PassManager`](crate::pass::PassManager)).
+    /// This records a snapshot of the whole arena.
+    pub fn with_arena<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Arena<S, C, I>) -> R,
+    {
+        self.log.ops.push(UndoOp::Snapshot(
+            self.arena.bbs.clone(),
+            self.arena.labels.clone(),
+            self.arena.weights.clone(),
+            self.arena.gens.clone(),
+        ));
+        f(self.arena)
+    }
+}
+
+impl<S, C, I: Idx> Transaction<'_, S, C, I>
+where
+    S: ForeachTarget<JumpTarget = I> + Clone,
+    C: ForeachTarget<JumpTarget = I> + Clone,
+{
+    /// See [`Arena::optimize`].
+    pub fn optimize(&mut self) -> OptimizeReport<I> {
+        self.with_arena(|arena| arena.optimize())
+    }
+}
+
+impl<S, C, I: Idx> Drop for Transaction<'_, S, C, I>
+where
+    ABB<S, C, I>: ForeachTarget<JumpTarget = I> + Clone,
+{
+    fn drop(&mut self) {
+        self.rollback_to(Checkpoint(0));
+    }
+}
@@ -0,0 +1,66 @@
+use super::*;
+use crate::jump::Unconditional;
+
+/// Profile data of the outgoing edges of a BB,
+/// see [`Arena::set_branch_weights`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct BranchWeights {
+    /// weights of taking and not taking the conditional jump
+    pub condjmp: Option<(u32, u32)>,
+
+    /// weights of the cases of the switch, followed by the default target
+    pub switch: Option<Vec<u32>>,
+}
+
+impl BranchWeights {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.condjmp.is_none() && self.switch.is_none()
+    }
+
+    /// Returns `true` if the weights fit the given BB.
+    pub fn fits<S, C, T>(&self, bb: &BasicBlockInner<S, C, T>) -> bool {
+        match bb {
+            BasicBlockInner::Concrete { condjmp, next, .. } => {
+                (self.condjmp.is_none() || condjmp.is_some())
+                    && match (&self.switch, next) {
+                        (None, _) => true,
+                        (Some(w), Unconditional::Switch(sw)) => w.len() == sw.cases.len() + 1,
+                        (Some(_), _) => false,
+                    }
+            }
+            BasicBlockInner::Placeholder { .. } => false,
+        }
+    }
+}
+
+impl<S, C, I: Idx> Arena<S, C, I> {
+    #[inline]
+    pub fn branch_weights(&self, bbid: I) -> Option<&BranchWeights> {
+        self.weights.get(&bbid)
+    }
+
+    /// Attaches profile data to the outgoing edges of the given BB,
+    /// and returns the previous weights. $weights is rejected if the BB
+    /// doesn't exist or the weights don't fit it (see [`BranchWeights::fits`]).
+    ///
+    /// The weights are dropped together with their BB, and follow it when it
+    /// gets renumbered or merged. Weights which don't fit their BB anymore
+    /// (e.g. after [`Arena::bbs_mut`]) are ignored by the analyses.
+    pub fn set_branch_weights(
+        &mut self,
+        bbid: I,
+        weights: BranchWeights,
+    ) -> Result<Option<BranchWeights>, BranchWeights> {
+        match self.bbs.get(&bbid) {
+            Some(bb) if weights.fits(&bb.inner) => Ok(self.weights.insert(bbid, weights)),
+            _ => Err(weights),
+        }
+    }
+
+    #[inline]
+    pub fn remove_branch_weights(&mut self, bbid: I) -> Option<BranchWeights> {
+        self.weights.remove(&bbid)
+    }
+}
@@ -0,0 +1,478 @@
+//! A human-readable, line-based text format for arenas.
+//!
+//! ```text
+//! label main
+//! pub bb0:
+//!     x = 1
+//!     if x bb2
+//!     jmp bb1
+//!
+//! bb1:
+//!     ret
+//!
+//! label puts
+//! extern placeholder bb2
+//! ```
+//!
+//! * `label <name>` attaches a label to the following BB
+//! * `[pub] bb<N>:` starts a concrete BB, followed by an optional
+//!   `unwind <target>` line, its statements,
+//!   an optional `if <condjmp>` line, and a terminator, which is one of
+//!   `halt`, `ret`, `unknown`, `jmp <target>`,
+//!   `switch <target>, <target>, ... else <target>`,
+//!   `ijmp <target>, <target>, ...` (`ijmp *` if any labeled BB
+//!   is a possible target) or `call <target> then <target>`,
+//!   where each target is either `bb<N>` or a label
+//! * `[pub] [extern] placeholder bb<N>` declares a placeholder
+//! * empty lines and lines starting with `#` are ignored
+//!
+//! Statements and conditional jumps are rendered and parsed through the
+//! [`Print`] and [`Parse`] traits; they must fit on a single line,
+//! and jump targets inside of them can't refer to labels.
+//! A statement which would be read as something else (e.g. `ret`,
+//! `call puts` or `# x`) is escaped with a leading `\`.
+
+use crate::bb::{BasicBlock, BasicBlockInner};
+use crate::jump::{Call, ForeachTarget, Switch, Unconditional};
+use crate::{Arena, BbId, OffendingIds, SetBbLabelError};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Renders a statement or conditional jump as a single line.
+pub trait Print {
+    fn print(&self, w: &mut dyn Write) -> fmt::Result;
+}
+
+/// Parses a statement or conditional jump from a single (trimmed) line.
+pub trait Parse: Sized {
+    fn parse(s: &str) -> Result<Self, String>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum ParseErrorKind {
+    #[cfg_attr(feature = "std", error("expected a BB header"))]
+    ExpectedHeader,
+
+    #[cfg_attr(feature = "std", error("invalid BB header"))]
+    InvalidHeader,
+
+    #[cfg_attr(feature = "std", error("BB {0} is defined multiple times"))]
+    DuplicateBb(BbId),
+
+    #[cfg_attr(feature = "std", error("label {0:?} is attached to nothing"))]
+    DanglingLabel(String),
+
+    #[cfg_attr(feature = "std", error("unknown label {0:?}"))]
+    UnknownLabel(String),
+
+    #[cfg_attr(feature = "std", error("BB is missing a terminator"))]
+    MissingTerminator,
+
+    #[cfg_attr(feature = "std", error("invalid terminator"))]
+    InvalidTerminator,
+
+    #[cfg_attr(
+        feature = "std",
+        error("expected a terminator after the conditional jump")
+    )]
+    ExpectedTerminator,
+
+    #[cfg_attr(feature = "std", error("multiple conditional jumps"))]
+    MultipleCondjmps,
+
+    #[cfg_attr(feature = "std", error("multiple unwind targets"))]
+    MultipleUnwindTargets,
+
+    #[cfg_attr(feature = "std", error("invalid statement: {0}"))]
+    Statement(String),
+
+    #[cfg_attr(feature = "std", error("invalid label: {0}"))]
+    Label(SetBbLabelError),
+
+    #[cfg_attr(feature = "std", error("invalid arena: {0}"))]
+    Check(OffendingIds),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+#[cfg_attr(feature = "std", error("line {line}: {kind}"))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ParseError {
+    /// 1-based line number, or 0 if the error isn't related to a single line
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+/// Parses a jump target of the form `bb<N>`.
+pub fn parse_target(s: &str) -> Option<BbId> {
+    let digits = s.strip_prefix("bb")?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Returns `true` if the given statement line would be parsed
+/// as something else than a statement, and thus needs to be escaped.
+fn needs_escape(line: &str) -> bool {
+    const PREFIXES: &[&str] = &[
+        "jmp ", "switch ", "call ", "ijmp ", "unwind ", "if ", "#", "\\",
+    ];
+    let line = line.trim();
+    matches!(line, "" | "halt" | "ret" | "unknown" | "ijmp")
+        || PREFIXES.iter().any(|p| line.starts_with(p))
+}
+
+/// Writes the arena in the text format.
+pub fn print<S, C>(arena: &Arena<S, C>, w: &mut dyn Write) -> fmt::Result
+where
+    S: Print,
+    C: Print,
+{
+    for (n, (&bbid, bb)) in arena.bbs().iter().enumerate() {
+        if n != 0 {
+            w.write_char('\n')?;
+        }
+        for i in arena.labels_of_bb(bbid) {
+            writeln!(w, "label {}", i)?;
+        }
+        if bb.is_public {
+            w.write_str("pub ")?;
+        }
+        match &bb.inner {
+            BasicBlockInner::Concrete {
+                statements,
+                condjmp,
+                next,
+                unwind,
+            } => {
+                writeln!(w, "bb{}:", bbid)?;
+                if let Some(t) = unwind {
+                    writeln!(w, "    unwind bb{}", t)?;
+                }
+                let mut line = String::new();
+                for i in statements {
+                    line.clear();
+                    i.print(&mut line)?;
+                    let escape = if needs_escape(&line) { "\\" } else { "" };
+                    writeln!(w, "    {}{}", escape, line)?;
+                }
+                if let Some(c) = condjmp {
+                    w.write_str("    if ")?;
+                    c.print(w)?;
+                    w.write_char('\n')?;
+                }
+                match next {
+                    Unconditional::Halt => w.write_str("    halt\n")?,
+                    Unconditional::Jump(t) => writeln!(w, "    jmp bb{}", t)?,
+                    Unconditional::Return => w.write_str("    ret\n")?,
+                    Unconditional::Unknown => w.write_str("    unknown\n")?,
+                    Unconditional::Switch(sw) => {
+                        w.write_str("    switch")?;
+                        for (i, t) in sw.cases.iter().enumerate() {
+                            let sep = if i == 0 { " " } else { ", " };
+                            write!(w, "{}bb{}", sep, t)?;
+                        }
+                        writeln!(w, " else bb{}", sw.default)?;
+                    }
+                    Unconditional::Indirect(None) => w.write_str("    ijmp *\n")?,
+                    Unconditional::Indirect(Some(ts)) => {
+                        w.write_str("    ijmp")?;
+                        for (i, t) in ts.iter().enumerate() {
+                            let sep = if i == 0 { " " } else { ", " };
+                            write!(w, "{}bb{}", sep, t)?;
+                        }
+                        w.write_char('\n')?;
+                    }
+                    Unconditional::Call(c) => {
+                        writeln!(w, "    call bb{} then bb{}", c.callee, c.continuation)?
+                    }
+                }
+            }
+            BasicBlockInner::Placeholder { is_extern } => {
+                if *is_extern {
+                    w.write_str("extern ")?;
+                }
+                writeln!(w, "placeholder bb{}", bbid)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renders the arena in the text format.
+pub fn to_string<S, C>(arena: &Arena<S, C>) -> String
+where
+    S: Print,
+    C: Print,
+{
+    let mut ret = String::new();
+    // writing into a `String` never fails
+    let _ = print(arena, &mut ret);
+    ret
+}
+
+enum Target {
+    Id(BbId),
+    Label(String, usize),
+}
+
+impl Target {
+    fn new(s: &str, lnum: usize) -> Self {
+        match parse_target(s) {
+            Some(t) => Target::Id(t),
+            None => Target::Label(s.to_string(), lnum),
+        }
+    }
+}
+
+struct PendingBb<S, C> {
+    bbid: BbId,
+    bb: BasicBlock<S, C, BbId>,
+    // terminator and unwind target, which reference targets by label
+    next: Option<Unconditional<Target>>,
+    unwind: Option<Target>,
+}
+
+/// Parses an arena from the text format and validates it
+/// via [`Arena::check`].
+pub fn parse<S, C>(s: &str) -> Result<Arena<S, C>, ParseError>
+where
+    S: Parse + ForeachTarget<JumpTarget = BbId>,
+    C: Parse + ForeachTarget<JumpTarget = BbId>,
+{
+    let err = |line, kind| ParseError { line, kind };
+    let mut bbs: Vec<PendingBb<S, C>> = Vec::new();
+    let mut labels: Vec<(String, BbId, usize)> = Vec::new();
+    let mut pending_labels: Vec<(String, usize)> = Vec::new();
+    // the BB which is currently being filled
+    let mut cur: Option<usize> = None;
+
+    for (lnum, line) in s.lines().enumerate() {
+        let lnum = lnum + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(idx) = cur {
+            let bb = &mut bbs[idx];
+            let (statements, condjmp, next) = match &mut bb.bb.inner {
+                BasicBlockInner::Concrete {
+                    statements,
+                    condjmp,
+                    next,
+                    ..
+                } => (statements, condjmp, next),
+                BasicBlockInner::Placeholder { .. } => unreachable!(),
+            };
+            if let Some(stmt) = line.strip_prefix('\\') {
+                if condjmp.is_some() {
+                    return Err(err(lnum, ParseErrorKind::ExpectedTerminator));
+                }
+                let stmt = S::parse(stmt).map_err(|e| err(lnum, ParseErrorKind::Statement(e)))?;
+                statements.push(stmt);
+                continue;
+            }
+            let term = match line {
+                "halt" => Some(Unconditional::Halt),
+                "ret" => Some(Unconditional::Return),
+                "unknown" => Some(Unconditional::Unknown),
+                _ => None,
+            };
+            if let Some(term) = term {
+                *next = term;
+                cur = None;
+            } else if let Some(t) = line.strip_prefix("jmp ") {
+                bb.next = Some(Unconditional::Jump(Target::new(t.trim(), lnum)));
+                cur = None;
+            } else if let Some(rest) = line.strip_prefix("switch ") {
+                let mut tokens: Vec<&str> = rest
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|i| !i.is_empty())
+                    .collect();
+                let default = match tokens.pop() {
+                    Some(default) if tokens.pop() == Some("else") => default,
+                    _ => return Err(err(lnum, ParseErrorKind::InvalidTerminator)),
+                };
+                bb.next = Some(Unconditional::Switch(Switch {
+                    cases: tokens.into_iter().map(|t| Target::new(t, lnum)).collect(),
+                    default: Target::new(default, lnum),
+                }));
+                cur = None;
+            } else if let Some(rest) = line.strip_prefix("call ") {
+                let (callee, continuation) = rest
+                    .split_once(" then ")
+                    .ok_or_else(|| err(lnum, ParseErrorKind::InvalidTerminator))?;
+                bb.next = Some(Unconditional::Call(Call {
+                    callee: Target::new(callee.trim(), lnum),
+                    continuation: Target::new(continuation.trim(), lnum),
+                }));
+                cur = None;
+            } else if line == "ijmp" || line.starts_with("ijmp ") {
+                let rest = line["ijmp".len()..].trim();
+                let targets = if rest == "*" {
+                    None
+                } else {
+                    Some(
+                        rest.split(|c: char| c == ',' || c.is_whitespace())
+                            .filter(|i| !i.is_empty())
+                            .map(|t| Target::new(t, lnum))
+                            .collect(),
+                    )
+                };
+                bb.next = Some(Unconditional::Indirect(targets));
+                cur = None;
+            } else if let Some(t) = line.strip_prefix("unwind ") {
+                if bb.unwind.is_some() {
+                    return Err(err(lnum, ParseErrorKind::MultipleUnwindTargets));
+                }
+                bb.unwind = Some(Target::new(t.trim(), lnum));
+            } else if let Some(c) = line.strip_prefix("if ") {
+                if condjmp.is_some() {
+                    return Err(err(lnum, ParseErrorKind::MultipleCondjmps));
+                }
+                let c = C::parse(c.trim()).map_err(|e| err(lnum, ParseErrorKind::Statement(e)))?;
+                *condjmp = Some(c);
+            } else if condjmp.is_some() {
+                // statements after the condjmp would change the semantics
+                return Err(err(lnum, ParseErrorKind::ExpectedTerminator));
+            } else {
+                let stmt = S::parse(line).map_err(|e| err(lnum, ParseErrorKind::Statement(e)))?;
+                statements.push(stmt);
+            }
+            continue;
+        }
+
+        if let Some(label) = line.strip_prefix("label ") {
+            pending_labels.push((label.trim().to_string(), lnum));
+            continue;
+        }
+
+        // BB header
+        let mut is_public = false;
+        let mut is_extern = false;
+        let mut rest = line;
+        if let Some(r) = rest.strip_prefix("pub ") {
+            is_public = true;
+            rest = r.trim_start();
+        }
+        if let Some(r) = rest.strip_prefix("extern ") {
+            is_extern = true;
+            rest = r.trim_start();
+        }
+        let (inner, bbid) = if let Some(r) = rest.strip_prefix("placeholder ") {
+            let bbid =
+                parse_target(r.trim()).ok_or_else(|| err(lnum, ParseErrorKind::InvalidHeader))?;
+            (BasicBlockInner::Placeholder { is_extern }, bbid)
+        } else if let Some(r) = rest.strip_suffix(':') {
+            if is_extern {
+                return Err(err(lnum, ParseErrorKind::InvalidHeader));
+            }
+            let bbid =
+                parse_target(r.trim()).ok_or_else(|| err(lnum, ParseErrorKind::ExpectedHeader))?;
+            (
+                BasicBlockInner::Concrete {
+                    statements: Vec::new(),
+                    condjmp: None,
+                    next: Unconditional::Unknown,
+                    unwind: None,
+                },
+                bbid,
+            )
+        } else {
+            return Err(err(lnum, ParseErrorKind::ExpectedHeader));
+        };
+        if bbs.iter().any(|i| i.bbid == bbid) {
+            return Err(err(lnum, ParseErrorKind::DuplicateBb(bbid)));
+        }
+        if inner.is_concrete() {
+            cur = Some(bbs.len());
+        }
+        for (label, lnum) in pending_labels.drain(..) {
+            labels.push((label, bbid, lnum));
+        }
+        bbs.push(PendingBb {
+            bbid,
+            bb: BasicBlock { inner, is_public },
+            next: None,
+            unwind: None,
+        });
+    }
+
+    if cur.is_some() {
+        return Err(err(s.lines().count(), ParseErrorKind::MissingTerminator));
+    }
+    if let Some((label, lnum)) = pending_labels.pop() {
+        return Err(err(lnum, ParseErrorKind::DanglingLabel(label)));
+    }
+
+    let resolve = |t| match t {
+        Target::Id(t) => Ok(t),
+        Target::Label(label, lnum) => labels
+            .iter()
+            .find(|i| i.0 == label)
+            .map(|i| i.1)
+            .ok_or_else(|| err(lnum, ParseErrorKind::UnknownLabel(label))),
+    };
+    let mut arena = Arena::new();
+    for PendingBb {
+        bbid,
+        mut bb,
+        next,
+        unwind,
+    } in bbs
+    {
+        if let (
+            BasicBlockInner::Concrete {
+                unwind: bbunwind, ..
+            },
+            Some(t),
+        ) = (&mut bb.inner, unwind)
+        {
+            *bbunwind = Some(resolve(t)?);
+        }
+        if let (BasicBlockInner::Concrete { next: bbnext, .. }, Some(next)) = (&mut bb.inner, next)
+        {
+            *bbnext = match next {
+                Unconditional::Halt => Unconditional::Halt,
+                Unconditional::Jump(t) => Unconditional::Jump(resolve(t)?),
+                Unconditional::Return => Unconditional::Return,
+                Unconditional::Unknown => Unconditional::Unknown,
+                Unconditional::Switch(sw) => Unconditional::Switch(Switch {
+                    cases: sw
+                        .cases
+                        .into_iter()
+                        .map(resolve)
+                        .collect::<Result<_, _>>()?,
+                    default: resolve(sw.default)?,
+                }),
+                Unconditional::Indirect(None) => Unconditional::Indirect(None),
+                Unconditional::Indirect(Some(ts)) => Unconditional::Indirect(Some(
+                    ts.into_iter().map(resolve).collect::<Result<_, _>>()?,
+                )),
+                Unconditional::Call(c) => Unconditional::Call(Call {
+                    callee: resolve(c.callee)?,
+                    continuation: resolve(c.continuation)?,
+                }),
+            };
+        }
+        arena.bbs_mut().insert(bbid, bb);
+    }
+    for (label, bbid, lnum) in labels {
+        arena
+            .set_label(label.into(), bbid, false)
+            .map_err(|e| err(lnum, ParseErrorKind::Label(e)))?;
+    }
+    arena.update_predecessors();
+    arena
+        .check()
+        .map_err(|e| err(0, ParseErrorKind::Check(e)))?;
+    Ok(arena)
+}
@@ -11,6 +11,13 @@ pub enum BasicBlockInner<S, C, T> {
         statements: Vec<S>,
         condjmp: Option<C>,
         next: jump::Unconditional<T>,
+        /// landing pad which any statement may transfer control to
+        /// (e.g. if an exception is thrown)
+        #[cfg_attr(
+            feature = "serde",
+            serde(default = "Option::default", skip_serializing_if = "Option::is_none")
+        )]
+        unwind: Option<T>,
     },
     /// placeholder for linker references to other files
     Placeholder { is_extern: bool },
@@ -23,6 +30,7 @@ impl<S, C, T> Default for BasicBlockInner<S, C, T> {
     }
 }
 
+#[allow(clippy::match_like_matches_macro)]
 impl<S, C, T> BasicBlockInner<S, C, T> {
     #[inline]
     pub fn is_concrete(&self) -> bool {
@@ -66,9 +74,13 @@ where
             statements,
             condjmp,
             next,
+            unwind,
         } = self
         {
             statements.foreach_target(&mut f);
+            if let Some(t) = unwind {
+                f(t);
+            }
             condjmp.foreach_target(&mut f);
             next.foreach_target(f);
         }
@@ -82,9 +94,13 @@ where
             statements,
             condjmp,
             next,
+            unwind,
         } = self
         {
             statements.foreach_target_mut(&mut f);
+            if let Some(t) = unwind {
+                f(t);
+            }
             condjmp.foreach_target_mut(&mut f);
             next.foreach_target_mut(f);
         }
@@ -0,0 +1,188 @@
+//! Strongly typed BB IDs.
+//!
+//! By default, an [`Arena`](crate::Arena) uses plain [`BbId`]s, but it can
+//! be parameterized with any [`Idx`] instead, e.g. a [`TypedBbId`] branded
+//! with a marker type per arena, which turns accidentally mixing IDs of
+//! different arenas (or with statement indices) into a type error:
+//!
+//! ```
+//! use yz_basic_block::{id::TypedBbId, jump::Dummy, Arena};
+//! struct Main;
+//! type MainId = TypedBbId<Main>;
+//! let arena = Arena::<Dummy<MainId>, Dummy<MainId>, MainId>::new();
+//! # drop(arena);
+//! ```
+//!
+//! ```compile_fail
+//! use yz_basic_block::{id::TypedBbId, jump::Dummy, Arena};
+//! struct Main;
+//! struct Lib;
+//! type MainId = TypedBbId<Main>;
+//! type LibId = TypedBbId<Lib>;
+//! let mut main = Arena::<Dummy<MainId>, Dummy<MainId>, MainId>::new();
+//! let lib = Arena::<Dummy<LibId>, Dummy<LibId>, LibId>::new();
+//! let id = main.reserve();
+//! lib.bbs().get(&id);
+//! ```
+//!
+//! The core API of the arena (`push`, `remove`, `reserve`, `fill`,
+//! `set_label`, `check`, `edit`, `transaction`), as well as `optimize`,
+//! the [`PassManager`](crate::pass::PassManager), `link`, `compact` and
+//! the construction of a [`Cfg`](crate::analysis::Cfg) work with any `Idx`;
+//! the [`ArenaBuilder`](crate::ArenaBuilder), the DOT output and the
+//! analyses built on top of the `Cfg` still require plain `BbId`s.
+
+use crate::BbId;
+use core::{cmp, fmt, hash, marker::PhantomData};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A type which can be used as BB ID inside of an [`Arena`](crate::Arena).
+pub trait Idx: Copy + Ord + fmt::Debug {
+    fn from_index(idx: usize) -> Self;
+    fn index(self) -> usize;
+}
+
+impl Idx for BbId {
+    #[inline(always)]
+    fn from_index(idx: usize) -> Self {
+        idx
+    }
+
+    #[inline(always)]
+    fn index(self) -> usize {
+        self
+    }
+}
+
+/// A [`BbId`] branded with the marker type `B`.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent, bound = ""))]
+pub struct TypedBbId<B: ?Sized> {
+    id: BbId,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    _brand: PhantomData<fn() -> B>,
+}
+
+impl<B: ?Sized> TypedBbId<B> {
+    #[inline(always)]
+    pub const fn new(id: BbId) -> Self {
+        Self {
+            id,
+            _brand: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn get(self) -> BbId {
+        self.id
+    }
+}
+
+impl<B: ?Sized> Idx for TypedBbId<B> {
+    #[inline(always)]
+    fn from_index(idx: usize) -> Self {
+        Self::new(idx)
+    }
+
+    #[inline(always)]
+    fn index(self) -> usize {
+        self.id
+    }
+}
+
+impl<B: ?Sized> From<BbId> for TypedBbId<B> {
+    #[inline(always)]
+    fn from(id: BbId) -> Self {
+        Self::new(id)
+    }
+}
+
+impl<B: ?Sized> From<TypedBbId<B>> for BbId {
+    #[inline(always)]
+    fn from(id: TypedBbId<B>) -> Self {
+        id.id
+    }
+}
+
+// manual impls to avoid unnecessary bounds on `B`
+
+impl<B: ?Sized> Clone for TypedBbId<B> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<B: ?Sized> Copy for TypedBbId<B> {}
+
+impl<B: ?Sized> PartialEq for TypedBbId<B> {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<B: ?Sized> Eq for TypedBbId<B> {}
+
+impl<B: ?Sized> PartialOrd for TypedBbId<B> {
+    #[inline(always)]
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<B: ?Sized> Ord for TypedBbId<B> {
+    #[inline(always)]
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl<B: ?Sized> hash::Hash for TypedBbId<B> {
+    #[inline(always)]
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<B: ?Sized> fmt::Debug for TypedBbId<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bb{}", self.id)
+    }
+}
+
+impl<B: ?Sized> fmt::Display for TypedBbId<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.id, f)
+    }
+}
+
+/// A BB ID together with the generation of its slot at the time it was
+/// obtained (via [`Arena::gen_id`](crate::Arena::gen_id)).
+/// [`Arena::get`](crate::Arena::get) returns `None` for stale IDs,
+/// i.e. if the BB was removed in the meantime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct GenId<I = BbId> {
+    id: I,
+    generation: u32,
+}
+
+impl<I: Copy> GenId<I> {
+    #[inline(always)]
+    pub(crate) fn new(id: I, generation: u32) -> Self {
+        Self { id, generation }
+    }
+
+    #[inline(always)]
+    pub fn id(&self) -> I {
+        self.id
+    }
+
+    #[inline(always)]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
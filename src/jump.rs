@@ -1,15 +1,52 @@
+use alloc::vec::Vec;
 use core::iter;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum Unconditional<T> {
     Halt,
     Jump(T),
     Return,
     Unknown,
+    Switch(Switch<T>),
+    /// A computed jump (e.g. a computed goto), which may jump to any of
+    /// the given targets, or, if `None`, to any labeled BB.
+    /// Unlike `Unknown`, the control flow never leaves the arena.
+    Indirect(Option<Vec<T>>),
+    Call(Call<T>),
+}
+
+/// A multi-way jump (jump table): jumps to `cases[i]` if the selector
+/// (which is implicitly computed by the statements of the BB) equals `i`,
+/// and to `default` otherwise.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Switch<T> {
+    pub cases: Vec<T>,
+    pub default: T,
+}
+
+impl<T: PartialEq> Switch<T> {
+    /// Returns the only jump target if all cases jump to the default target.
+    pub fn single_target(&self) -> Option<&T> {
+        if self.cases.iter().all(|i| *i == self.default) {
+            Some(&self.default)
+        } else {
+            None
+        }
+    }
+}
+
+/// A call of `callee` (usually a labeled BB or an extern placeholder),
+/// which resumes at `continuation` after the callee returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Call<T> {
+    pub callee: T,
+    pub continuation: T,
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
@@ -54,8 +91,12 @@ impl<T> ForeachTarget for Unconditional<T> {
     where
         F: FnMut(&Self::JumpTarget),
     {
-        if let Unconditional::Jump(t) = self {
-            f(t);
+        match self {
+            Unconditional::Jump(t) => f(t),
+            Unconditional::Switch(sw) => sw.foreach_target(f),
+            Unconditional::Indirect(Some(ts)) => ts.iter().for_each(f),
+            Unconditional::Call(c) => c.foreach_target(f),
+            _ => {}
         }
     }
 
@@ -64,12 +105,60 @@ impl<T> ForeachTarget for Unconditional<T> {
     where
         F: FnMut(&mut Self::JumpTarget),
     {
-        if let Unconditional::Jump(t) = self {
-            f(t);
+        match self {
+            Unconditional::Jump(t) => f(t),
+            Unconditional::Switch(sw) => sw.foreach_target_mut(f),
+            Unconditional::Indirect(Some(ts)) => ts.iter_mut().for_each(f),
+            Unconditional::Call(c) => c.foreach_target_mut(f),
+            _ => {}
         }
     }
 }
 
+impl<T> ForeachTarget for Switch<T> {
+    type JumpTarget = T;
+
+    #[inline]
+    fn foreach_target<F>(&self, mut f: F)
+    where
+        F: FnMut(&Self::JumpTarget),
+    {
+        self.cases.iter().for_each(&mut f);
+        f(&self.default);
+    }
+
+    #[inline]
+    fn foreach_target_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Self::JumpTarget),
+    {
+        self.cases.iter_mut().for_each(&mut f);
+        f(&mut self.default);
+    }
+}
+
+impl<T> ForeachTarget for Call<T> {
+    type JumpTarget = T;
+
+    #[inline]
+    fn foreach_target<F>(&self, mut f: F)
+    where
+        F: FnMut(&Self::JumpTarget),
+    {
+        f(&self.callee);
+        f(&self.continuation);
+    }
+
+    #[inline]
+    fn foreach_target_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Self::JumpTarget),
+    {
+        f(&mut self.callee);
+        f(&mut self.continuation);
+    }
+}
+
 impl<C, T> ForeachTarget for C
 where
     for<'a> &'a C: iter::IntoIterator<Item = &'a T>,
@@ -4,11 +4,23 @@
 extern crate alloc;
 extern crate core;
 
+pub mod analysis;
 mod arena;
+pub mod asm;
 mod bb;
+pub mod id;
 pub mod jump;
+pub mod pass;
+pub mod ssa;
+pub mod var;
 
-pub use arena::{Arena, OffendingIds, SetBbLabelError};
+pub use arena::{
+    Arena, ArenaBuilder, ArenaEditor, BbMut, BranchWeights, BuildError, Checkpoint, CompactOrder,
+    FillError, LinkError, LinkLoc, OffendingIds, OptimizeReport, Predecessors, SetBbLabelError,
+    Transaction, UndoLog,
+};
+#[cfg(feature = "dot")]
+pub use arena::{DebugFormatter, DotFormatter};
 pub use bb::{BasicBlock, BasicBlockInner};
 pub type BbId = usize;
 pub type Label = alloc::borrow::Cow<'static, str>;
@@ -0,0 +1,208 @@
+//! Optimization passes and a pass manager to run them.
+
+use crate::id::Idx;
+use crate::jump::ForeachTarget;
+use crate::{Arena, BbId, OptimizeReport};
+use alloc::{boxed::Box, vec::Vec};
+
+pub trait Pass<S, C, I = BbId> {
+    /// A short, unique name of the pass, used to identify it.
+    fn name(&self) -> &str;
+
+    /// Runs the pass once, returns `true` if the arena was modified.
+    /// All modifications should be recorded in `report`.
+    fn run(&mut self, arena: &mut Arena<S, C, I>, report: &mut OptimizeReport<I>) -> bool;
+}
+
+/// Removes all BBs which aren't reachable from any public BB,
+/// and all labels which point to them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RemoveUnreachable;
+
+/// Replaces switches whose cases all jump to the same BB
+/// by an unconditional jump.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CollapseSwitches;
+
+/// Redirects all references to non-public, empty BBs which only jump
+/// to another BB, to the final target of the jump.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ForwardJumps;
+
+/// Merges non-public BBs into their only predecessor,
+/// if that unconditionally jumps to them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MergeChains;
+
+impl<S, C, I: Idx> Pass<S, C, I> for RemoveUnreachable
+where
+    S: ForeachTarget<JumpTarget = I>,
+    C: ForeachTarget<JumpTarget = I>,
+{
+    #[inline]
+    fn name(&self) -> &str {
+        "remove-unreachable"
+    }
+
+    #[inline]
+    fn run(&mut self, arena: &mut Arena<S, C, I>, report: &mut OptimizeReport<I>) -> bool {
+        arena.remove_unreachable(report)
+    }
+}
+
+impl<S, C, I: Idx> Pass<S, C, I> for CollapseSwitches
+where
+    S: ForeachTarget<JumpTarget = I>,
+    C: ForeachTarget<JumpTarget = I>,
+{
+    #[inline]
+    fn name(&self) -> &str {
+        "collapse-switches"
+    }
+
+    #[inline]
+    fn run(&mut self, arena: &mut Arena<S, C, I>, report: &mut OptimizeReport<I>) -> bool {
+        arena.collapse_switches(report)
+    }
+}
+
+impl<S, C, I: Idx> Pass<S, C, I> for ForwardJumps
+where
+    S: ForeachTarget<JumpTarget = I>,
+    C: ForeachTarget<JumpTarget = I>,
+{
+    #[inline]
+    fn name(&self) -> &str {
+        "forward-jumps"
+    }
+
+    #[inline]
+    fn run(&mut self, arena: &mut Arena<S, C, I>, report: &mut OptimizeReport<I>) -> bool {
+        arena.forward_jumps(report)
+    }
+}
+
+impl<S, C, I: Idx> Pass<S, C, I> for MergeChains
+where
+    S: ForeachTarget<JumpTarget = I>,
+    C: ForeachTarget<JumpTarget = I>,
+{
+    #[inline]
+    fn name(&self) -> &str {
+        "merge-chains"
+    }
+
+    #[inline]
+    fn run(&mut self, arena: &mut Arena<S, C, I>, report: &mut OptimizeReport<I>) -> bool {
+        arena.merge_chains(report)
+    }
+}
+
+/// Statistics about a single pass, accumulated over all runs of a [`PassManager`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PassStats {
+    /// how often the pass was run
+    pub runs: usize,
+    /// how often the pass modified the arena
+    pub changes: usize,
+}
+
+type BoxedPass<S, C, I> = Box<dyn Pass<S, C, I>>;
+
+/// Runs a list of passes in order, until none of them modifies the arena anymore.
+pub struct PassManager<S, C, I = BbId> {
+    passes: Vec<(BoxedPass<S, C, I>, PassStats)>,
+    iterations: usize,
+}
+
+impl<S, C, I> Default for PassManager<S, C, I> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            passes: Vec::new(),
+            iterations: 0,
+        }
+    }
+}
+
+impl<S, C, I> PassManager<S, C, I> {
+    /// Creates an empty pass manager.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends a pass, which is run after all previously added passes.
+    pub fn add<P>(&mut self, pass: P) -> &mut Self
+    where
+        P: Pass<S, C, I> + 'static,
+    {
+        self.passes.push((Box::new(pass), PassStats::default()));
+        self
+    }
+
+    /// Removes all passes with the given name,
+    /// returns `true` if any pass was removed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let old_len = self.passes.len();
+        self.passes.retain(|(p, _)| p.name() != name);
+        self.passes.len() != old_len
+    }
+
+    /// Returns the names of all passes in the order they are run.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.passes.iter().map(|(p, _)| p.name())
+    }
+
+    /// Returns the statistics of all passes, in the order they are run.
+    pub fn stats(&self) -> impl Iterator<Item = (&str, &PassStats)> {
+        self.passes.iter().map(|(p, s)| (p.name(), s))
+    }
+
+    /// Returns how many rounds over all passes were run in total.
+    #[inline(always)]
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Runs every pass once, in order.
+    /// Returns `true` if any pass modified the arena.
+    pub fn run_once(&mut self, arena: &mut Arena<S, C, I>, report: &mut OptimizeReport<I>) -> bool {
+        self.iterations += 1;
+        let mut modified = false;
+        for (pass, stats) in self.passes.iter_mut() {
+            stats.runs += 1;
+            if pass.run(arena, report) {
+                stats.changes += 1;
+                modified = true;
+            }
+        }
+        modified
+    }
+
+    /// Runs all passes until a fixpoint is reached.
+    pub fn run(&mut self, arena: &mut Arena<S, C, I>) -> OptimizeReport<I> {
+        let mut report = OptimizeReport::default();
+        while self.run_once(arena, &mut report) {}
+        report
+    }
+}
+
+impl<S, C, I> PassManager<S, C, I>
+where
+    S: ForeachTarget<JumpTarget = I> + 'static,
+    C: ForeachTarget<JumpTarget = I> + 'static,
+    I: Idx + 'static,
+{
+    /// Creates a pass manager with the passes used by [`Arena::optimize`]:
+    /// [`RemoveUnreachable`], [`CollapseSwitches`], [`ForwardJumps`]
+    /// and [`MergeChains`].
+    pub fn standard() -> Self {
+        let mut ret = Self::new();
+        ret.add(RemoveUnreachable)
+            .add(CollapseSwitches)
+            .add(ForwardJumps)
+            .add(MergeChains);
+        ret
+    }
+}
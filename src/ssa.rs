@@ -0,0 +1,521 @@
+//! SSA construction and destruction.
+//!
+//! Instead of phi nodes, the SSA form uses block parameters: every BB
+//! can have a list of parameters, and every edge into it passes
+//! a matching list of arguments. These are stored in side tables next to
+//! the arena, because jump targets are plain [`BbId`]s, which can appear
+//! in statements, `condjmp` and the terminator alike, and all edges between
+//! the same pair of BBs always pass the same arguments.
+
+use crate::analysis::{Cfg, Dominators, Liveness};
+use crate::bb::BasicBlockInner;
+use crate::jump::{ForeachTarget, Unconditional};
+use crate::var::ForeachVar;
+use crate::{Arena, BasicBlock, BbId};
+use alloc::collections::{BTreeMap as Map, BTreeSet};
+use alloc::vec::Vec;
+
+/// Statements which can express a plain copy `dst = src`,
+/// needed to destruct the SSA form.
+pub trait MakeCopy<V> {
+    fn make_copy(dst: V, src: V) -> Self;
+}
+
+/// An arena in SSA form.
+#[derive(Debug)]
+pub struct Ssa<S, C, V> {
+    pub arena: Arena<S, C>,
+
+    /// parameters of every BB which has any
+    pub params: Map<BbId, Vec<V>>,
+
+    /// arguments passed along the edge `(from, to)`, matching `params[to]`
+    pub args: Map<(BbId, BbId), Vec<V>>,
+}
+
+fn rename_uses<X, V>(x: &mut X, stacks: &Map<V, Vec<V>>)
+where
+    X: ForeachVar<Var = V>,
+    V: Clone + Ord,
+{
+    x.foreach_use_mut(|v| {
+        if let Some(n) = stacks.get(v).and_then(|s| s.last()) {
+            *v = n.clone();
+        }
+    });
+}
+
+fn rename_defs<X, V, F>(x: &mut X, stacks: &mut Map<V, Vec<V>>, pushed: &mut Vec<V>, fresh: &mut F)
+where
+    X: ForeachVar<Var = V>,
+    V: Clone + Ord,
+    F: FnMut(&V) -> V,
+{
+    x.foreach_def_mut(|v| {
+        let n = fresh(v);
+        stacks.entry(v.clone()).or_default().push(n.clone());
+        pushed.push(core::mem::replace(v, n));
+    });
+}
+
+/// Returns `true` if `x` defines any of the variables in `vars`.
+fn defines_any<X, V>(x: &X, vars: &BTreeSet<V>) -> bool
+where
+    X: ForeachVar<Var = V>,
+    V: Ord,
+{
+    let mut ret = false;
+    x.foreach_def(|v| ret |= vars.contains(v));
+    ret
+}
+
+/// Splits the BB $bbid, which unwinds to $pad, after every statement which
+/// redefines a variable of $pad_live that another unwind point (a later
+/// statement) or a regular edge to $pad could observe. Afterwards, the
+/// unwind edges of the resulting BBs pass the values at their entry.
+fn split_unwind_points<S, C, V>(
+    arena: &mut Arena<S, C>,
+    mut bbid: BbId,
+    pad: BbId,
+    pad_live: &BTreeSet<V>,
+) where
+    S: ForeachTarget<JumpTarget = BbId> + ForeachVar<Var = V>,
+    C: ForeachTarget<JumpTarget = BbId> + ForeachVar<Var = V>,
+    V: Ord,
+{
+    loop {
+        let (split_after, is_empty) = match &arena.bbs()[&bbid].inner {
+            BasicBlockInner::Concrete {
+                statements,
+                condjmp,
+                next,
+                unwind: Some(_),
+            } => {
+                let mut jumps_to_pad = false;
+                let mut f = |&t: &BbId| jumps_to_pad |= t == pad;
+                statements.foreach_target(&mut f);
+                condjmp.foreach_target(&mut f);
+                next.foreach_target(&mut f);
+                let len = statements.len();
+                let mut split_after = statements
+                    .iter()
+                    .position(|i| defines_any(i, pad_live))
+                    .filter(|&i| i + 1 < len || jumps_to_pad);
+                // the regular edges to the pad see the definitions of the condjmp, too
+                if split_after.is_none()
+                    && jumps_to_pad
+                    && condjmp.iter().any(|c| defines_any(c, pad_live))
+                {
+                    split_after = Some(len.saturating_sub(1));
+                }
+                (split_after, len == 0)
+            }
+            _ => return,
+        };
+        let split_after = match split_after {
+            Some(i) => i,
+            None => return,
+        };
+        if is_empty {
+            // without statements, nothing can unwind
+            if let BasicBlockInner::Concrete { unwind, .. } =
+                &mut arena.bbs_mut().get_mut(&bbid).unwrap().inner
+            {
+                *unwind = None;
+            }
+            return;
+        }
+
+        let tail = arena.reserve();
+        let tail_bb = match &mut arena.bbs_mut().get_mut(&bbid).unwrap().inner {
+            BasicBlockInner::Concrete {
+                statements,
+                condjmp,
+                next,
+                ..
+            } => {
+                let statements = statements.split_off(split_after + 1);
+                let unwind = if statements.is_empty() {
+                    None
+                } else {
+                    Some(pad)
+                };
+                BasicBlockInner::Concrete {
+                    statements,
+                    condjmp: condjmp.take(),
+                    next: core::mem::replace(next, Unconditional::Jump(tail)),
+                    unwind,
+                }
+            }
+            BasicBlockInner::Placeholder { .. } => unreachable!(),
+        };
+        arena.bbs_mut().insert(
+            tail,
+            BasicBlock {
+                inner: tail_bb,
+                is_public: false,
+            },
+        );
+        // the tail ends with the branches of the BB
+        if let Some(w) = arena.remove_branch_weights(bbid) {
+            let _ = arena.set_branch_weights(tail, w);
+        }
+        bbid = tail;
+    }
+}
+
+impl<S, C, V> Ssa<S, C, V>
+where
+    S: ForeachTarget<JumpTarget = BbId> + ForeachVar<Var = V>,
+    C: ForeachTarget<JumpTarget = BbId> + ForeachVar<Var = V>,
+    V: Clone + Ord,
+{
+    /// Converts the arena into (pruned) SSA form.
+    ///
+    /// `fresh` is called for every definition and every block parameter
+    /// and must return a new, unique variable derived from the original one.
+    /// Variables which are used before any definition keep their original
+    /// name. BBs which aren't reachable from any public or labeled BB
+    /// are left untouched.
+    ///
+    /// Public and labeled BBs can also be entered from outside of the arena,
+    /// which passes the original variables. Thus their parameters keep the
+    /// original name of their variable, too.
+    ///
+    /// A landing pad can observe a different value of a variable depending
+    /// on the statement which unwinds. Thus a BB is split after every
+    /// statement which redefines a variable that is live at its landing
+    /// pad, if a later statement could unwind, too. Every unwind edge then
+    /// passes the values at the start of its BB, and the landing pad gets
+    /// a parameter for every such variable.
+    pub fn construct<F>(mut arena: Arena<S, C>, mut fresh: F) -> Self
+    where
+        F: FnMut(&V) -> V,
+    {
+        // make every unwind point which sees a redefinition its own edge
+        {
+            let cfg = Cfg::from_arena(&arena);
+            let live = Liveness::new(&cfg, &arena);
+            for &n in cfg.rpo() {
+                if let Some(p) = cfg.unwind(n) {
+                    if let Some(l) = live.live_in(p) {
+                        split_unwind_points(&mut arena, n, p, l);
+                    }
+                }
+            }
+        }
+
+        let cfg = Cfg::from_arena(&arena);
+        let dom = Dominators::new(&cfg);
+        let live = Liveness::new(&cfg, &arena);
+
+        // collect the BBs which define every variable, and the landing pads
+        // which see both the old and the new value
+        let mut defsites: Map<V, BTreeSet<BbId>> = Map::new();
+        let mut pads: Map<V, BTreeSet<BbId>> = Map::new();
+        for &n in cfg.rpo() {
+            if let BasicBlockInner::Concrete {
+                statements,
+                condjmp,
+                ..
+            } = &arena.bbs()[&n].inner
+            {
+                let pad = cfg.unwind(n).and_then(|p| live.live_in(p).map(|l| (p, l)));
+                let mut f = |v: &V| {
+                    if let Some((p, l)) = pad {
+                        if l.contains(v) {
+                            pads.entry(v.clone()).or_default().insert(p);
+                        }
+                    }
+                    defsites.entry(v.clone()).or_default().insert(n);
+                };
+                statements.foreach_def(&mut f);
+                condjmp.foreach_def(&mut f);
+            }
+        }
+
+        // place the block parameters at the iterated dominance frontiers
+        let mut param_vars: Map<BbId, Vec<V>> = Map::new();
+        for (v, sites) in defsites {
+            let mut has_param = BTreeSet::new();
+            let mut todo: Vec<_> = sites.into_iter().collect();
+            for p in pads.remove(&v).unwrap_or_default() {
+                if has_param.insert(p) {
+                    param_vars.entry(p).or_default().push(v.clone());
+                    todo.push(p);
+                }
+            }
+            while let Some(n) = todo.pop() {
+                for d in dom.frontier(n) {
+                    if !live.live_in(d).map(|l| l.contains(&v)).unwrap_or(false) {
+                        continue;
+                    }
+                    if has_param.insert(d) {
+                        param_vars.entry(d).or_default().push(v.clone());
+                        todo.push(d);
+                    }
+                }
+            }
+        }
+
+        let entries: BTreeSet<BbId> = cfg.entries().iter().copied().collect();
+
+        // rename all variables in dominator tree preorder
+        let mut params: Map<BbId, Vec<V>> = Map::new();
+        let mut args: Map<(BbId, BbId), Vec<V>> = Map::new();
+        let mut stacks: Map<V, Vec<V>> = Map::new();
+        let mut todo: Vec<(BbId, bool)> = dom.roots().map(|i| (i, true)).collect();
+        todo.reverse();
+        let mut pushed_per_bb: Map<BbId, Vec<V>> = Map::new();
+        let bbs = arena.bbs_mut();
+        while let Some((n, enter)) = todo.pop() {
+            if !enter {
+                for v in pushed_per_bb.remove(&n).unwrap_or_default() {
+                    stacks.get_mut(&v).unwrap().pop();
+                }
+                continue;
+            }
+
+            let mut pushed = Vec::new();
+            if let Some(pvs) = param_vars.get(&n) {
+                let ps = pvs
+                    .iter()
+                    .map(|v| {
+                        let p = if entries.contains(&n) {
+                            v.clone()
+                        } else {
+                            fresh(v)
+                        };
+                        stacks.entry(v.clone()).or_default().push(p.clone());
+                        pushed.push(v.clone());
+                        p
+                    })
+                    .collect();
+                params.insert(n, ps);
+            }
+            // unwind edges pass the values at the start of the BB
+            let cur = |v: &V, stacks: &Map<V, Vec<V>>| {
+                stacks.get(v).and_then(|s| s.last()).unwrap_or(v).clone()
+            };
+            if let Some(p) = cfg.unwind(n).filter(|&p| cfg.is_unwind_edge(n, p)) {
+                if let Some(pvs) = param_vars.get(&p) {
+                    args.insert((n, p), pvs.iter().map(|v| cur(v, &stacks)).collect());
+                }
+            }
+            if let BasicBlockInner::Concrete {
+                statements,
+                condjmp,
+                ..
+            } = &mut bbs.get_mut(&n).unwrap().inner
+            {
+                for i in statements.iter_mut() {
+                    rename_uses(i, &stacks);
+                    rename_defs(i, &mut stacks, &mut pushed, &mut fresh);
+                }
+                if let Some(c) = condjmp {
+                    rename_uses(c, &stacks);
+                    rename_defs(c, &mut stacks, &mut pushed, &mut fresh);
+                }
+            }
+            for &s in cfg.succs(n) {
+                if cfg.is_unwind_edge(n, s) {
+                    continue;
+                }
+                if let Some(pvs) = param_vars.get(&s) {
+                    args.insert((n, s), pvs.iter().map(|v| cur(v, &stacks)).collect());
+                }
+            }
+            pushed_per_bb.insert(n, pushed);
+
+            todo.push((n, false));
+            let mut children: Vec<_> = dom.children(n).collect();
+            children.reverse();
+            todo.extend(children.into_iter().map(|i| (i, true)));
+        }
+        arena.rebuild_preds();
+
+        Self {
+            arena,
+            params,
+            args,
+        }
+    }
+
+    /// Returns the parameters of the given BB.
+    pub fn params_of(&self, bbid: BbId) -> &[V] {
+        self.params.get(&bbid).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Returns the arguments passed along the edge `(from, to)`.
+    pub fn args_of(&self, from: BbId, to: BbId) -> &[V] {
+        self.args.get(&(from, to)).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+/// Sequentializes the parallel copy `dsts[i] = srcs[i]`,
+/// using temporaries from `fresh` to break cycles.
+fn sequentialize<V, F>(mut copies: Vec<(V, V)>, fresh: &mut F) -> Vec<(V, V)>
+where
+    V: Clone + Ord,
+    F: FnMut(&V) -> V,
+{
+    copies.retain(|(d, s)| d != s);
+    let mut ret = Vec::with_capacity(copies.len());
+    while !copies.is_empty() {
+        if let Some(pos) = copies
+            .iter()
+            .position(|(d, _)| !copies.iter().any(|(_, s)| s == d))
+        {
+            ret.push(copies.remove(pos));
+        } else {
+            // every destination is still needed as a source, break the cycle
+            let d = copies[0].0.clone();
+            let tmp = fresh(&d);
+            for (_, s) in copies.iter_mut() {
+                if *s == d {
+                    *s = tmp.clone();
+                }
+            }
+            ret.push((tmp, d));
+        }
+    }
+    ret
+}
+
+impl<S, C, V> Ssa<S, C, V>
+where
+    S: ForeachTarget<JumpTarget = BbId> + MakeCopy<V>,
+    C: ForeachTarget<JumpTarget = BbId>,
+    V: Clone + Ord,
+{
+    /// Converts the SSA form back into a plain arena, by replacing
+    /// the block parameters with copies on the incoming edges.
+    ///
+    /// The copies are appended to the source BB if it has no other
+    /// successor, otherwise (or if it unwinds to the target)
+    /// the edge is split by a new BB.
+    /// `fresh` is used to create temporaries for cyclic copies.
+    ///
+    /// Edges without an explicit jump target (implied by
+    /// [`Unconditional::Unknown`] and `Unconditional::Indirect(None)`)
+    /// can't be split. Instead, the copies of all such edges of a BB are
+    /// merged into a single parallel copy at the end of it, which panics if
+    /// they assign different values to the same parameter. This can't happen
+    /// for an unmodified result of [`Ssa::construct`], because these edges
+    /// only lead to entry BBs, whose parameters keep their original names.
+    pub fn destruct<F>(self, mut fresh: F) -> Arena<S, C>
+    where
+        F: FnMut(&V) -> V,
+    {
+        let Self {
+            mut arena,
+            params,
+            args,
+        } = self;
+
+        // parallel copies of the edges without explicit target, per source BB
+        let mut implicit: Map<BbId, Vec<(V, V)>> = Map::new();
+
+        for ((from, to), a) in args {
+            let ps = match params.get(&to) {
+                Some(ps) if arena.bbs().contains_key(&to) => ps,
+                _ => continue,
+            };
+            let bb = match arena.bbs().get(&from) {
+                Some(bb) => bb,
+                None => continue,
+            };
+
+            let is_implicit = match &bb.inner {
+                BasicBlockInner::Concrete {
+                    next: Unconditional::Unknown,
+                    ..
+                } => arena.bbs()[&to].is_public || arena.labels_of_bb(to).next().is_some(),
+                BasicBlockInner::Concrete {
+                    next: Unconditional::Indirect(None),
+                    ..
+                } => arena.labels_of_bb(to).next().is_some(),
+                _ => false,
+            };
+            if is_implicit {
+                let merged = implicit.entry(from).or_default();
+                for (d, s) in ps.iter().cloned().zip(a) {
+                    match merged.iter().find(|(md, _)| *md == d) {
+                        Some((_, ms)) => assert!(
+                            *ms == s,
+                            "conflicting copies on the implicit edges of bb{}",
+                            from
+                        ),
+                        None => merged.push((d, s)),
+                    }
+                }
+                continue;
+            }
+
+            let copies = sequentialize(ps.iter().cloned().zip(a).collect(), &mut fresh);
+            if copies.is_empty() {
+                continue;
+            }
+            let mut copies: Vec<S> = copies
+                .into_iter()
+                .map(|(d, s)| S::make_copy(d, s))
+                .collect();
+
+            let mut is_single = true;
+            bb.foreach_target(|&t| {
+                if t != to {
+                    is_single = false;
+                }
+            });
+            // copies at the end of the BB aren't executed when it unwinds
+            let can_append = match &bb.inner {
+                BasicBlockInner::Concrete {
+                    condjmp, unwind, ..
+                } => is_single && condjmp.is_none() && *unwind != Some(to),
+                BasicBlockInner::Placeholder { .. } => false,
+            };
+
+            if can_append {
+                if let BasicBlockInner::Concrete { statements, .. } =
+                    &mut arena.bbs_mut().get_mut(&from).unwrap().inner
+                {
+                    statements.append(&mut copies);
+                }
+            } else {
+                let split = arena
+                    .push(BasicBlock {
+                        inner: BasicBlockInner::Concrete {
+                            statements: copies,
+                            condjmp: None,
+                            next: Unconditional::Jump(to),
+                            unwind: None,
+                        },
+                        is_public: false,
+                    })
+                    .ok()
+                    .expect("unable to split edge");
+                arena
+                    .bbs_mut()
+                    .get_mut(&from)
+                    .unwrap()
+                    .foreach_target_mut(|t| {
+                        if *t == to {
+                            *t = split;
+                        }
+                    });
+            }
+        }
+
+        for (from, copies) in implicit {
+            let copies = sequentialize(copies, &mut fresh);
+            if let BasicBlockInner::Concrete { statements, .. } =
+                &mut arena.bbs_mut().get_mut(&from).unwrap().inner
+            {
+                statements.extend(copies.into_iter().map(|(d, s)| S::make_copy(d, s)));
+            }
+        }
+
+        arena.rebuild_preds();
+        arena
+    }
+}
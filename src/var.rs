@@ -0,0 +1,110 @@
+use crate::jump::Dummy;
+use core::iter;
+
+/// Def/use information of statements and conditional jumps,
+/// analogous to [`ForeachTarget`](crate::jump::ForeachTarget).
+///
+/// Inside of a single statement, all uses are considered to happen
+/// before all definitions.
+pub trait ForeachVar {
+    type Var;
+
+    /// Calls `f` for every variable which is read.
+    fn foreach_use<F>(&self, f: F)
+    where
+        F: FnMut(&Self::Var);
+
+    /// Calls `f` for every variable which is written.
+    fn foreach_def<F>(&self, f: F)
+    where
+        F: FnMut(&Self::Var);
+
+    fn foreach_use_mut<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Self::Var);
+
+    fn foreach_def_mut<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Self::Var);
+}
+
+impl<T> ForeachVar for Dummy<T> {
+    type Var = T;
+
+    #[inline]
+    fn foreach_use<F>(&self, _f: F)
+    where
+        F: FnMut(&Self::Var),
+    {
+    }
+
+    #[inline]
+    fn foreach_def<F>(&self, _f: F)
+    where
+        F: FnMut(&Self::Var),
+    {
+    }
+
+    #[inline]
+    fn foreach_use_mut<F>(&mut self, _f: F)
+    where
+        F: FnMut(&mut Self::Var),
+    {
+    }
+
+    #[inline]
+    fn foreach_def_mut<F>(&mut self, _f: F)
+    where
+        F: FnMut(&mut Self::Var),
+    {
+    }
+}
+
+impl<C, T> ForeachVar for C
+where
+    for<'a> &'a C: iter::IntoIterator<Item = &'a T>,
+    for<'a> &'a mut C: iter::IntoIterator<Item = &'a mut T>,
+    T: ForeachVar,
+{
+    type Var = T::Var;
+
+    #[inline]
+    fn foreach_use<F>(&self, mut f: F)
+    where
+        F: FnMut(&Self::Var),
+    {
+        for i in self {
+            i.foreach_use(&mut f);
+        }
+    }
+
+    #[inline]
+    fn foreach_def<F>(&self, mut f: F)
+    where
+        F: FnMut(&Self::Var),
+    {
+        for i in self {
+            i.foreach_def(&mut f);
+        }
+    }
+
+    #[inline]
+    fn foreach_use_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Self::Var),
+    {
+        for i in self {
+            i.foreach_use_mut(&mut f);
+        }
+    }
+
+    #[inline]
+    fn foreach_def_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Self::Var),
+    {
+        for i in self {
+            i.foreach_def_mut(&mut f);
+        }
+    }
+}
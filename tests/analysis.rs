@@ -0,0 +1,368 @@
+use yz_basic_block::{
+    analysis::{
+        BlockFrequency, CallGraph, Cfg, ControlDependence, Dominators, LoopForest, PostDominators,
+    },
+    jump::{Call, Switch, Unconditional},
+    BasicBlockInner, BranchWeights,
+};
+
+mod common;
+use common::{build, TestArena};
+
+use Unconditional::{Halt, Jump, Return, Unknown};
+
+#[test]
+fn dom_diamond() {
+    //   0
+    //  / \
+    // 1   2
+    //  \ /
+    //   3
+    let arena = build(&[
+        (Some(2), Jump(1)),
+        (None, Jump(3)),
+        (None, Jump(3)),
+        (None, Return),
+    ]);
+    let cfg = Cfg::from_arena(&arena);
+    assert_eq!(cfg.entries(), &[0]);
+    assert_eq!(cfg.rpo()[0], 0);
+    assert_eq!(cfg.exits(), &[3]);
+    let dom = Dominators::new(&cfg);
+    assert_eq!(dom.idom(0), None);
+    assert_eq!(dom.idom(1), Some(0));
+    assert_eq!(dom.idom(2), Some(0));
+    assert_eq!(dom.idom(3), Some(0));
+    assert!(dom.dominates(0, 3));
+    assert!(dom.dominates(3, 3));
+    assert!(!dom.strictly_dominates(3, 3));
+    assert!(!dom.dominates(1, 3));
+    assert_eq!(dom.roots().collect::<Vec<_>>(), [0]);
+    let mut ch: Vec<_> = dom.children(0).collect();
+    ch.sort_unstable();
+    assert_eq!(ch, [1, 2, 3]);
+    assert_eq!(dom.frontier(1).collect::<Vec<_>>(), [3]);
+    assert_eq!(dom.frontier(2).collect::<Vec<_>>(), [3]);
+    assert_eq!(dom.frontier(0).count(), 0);
+    assert_eq!(dom.frontier(3).count(), 0);
+}
+
+#[test]
+fn dom_loop_and_unreachable() {
+    // 0 -> 1 -> 2 -> 1, 2 -> 3; 4 is unreachable
+    let arena = build(&[
+        (None, Jump(1)),
+        (None, Jump(2)),
+        (Some(1), Jump(3)),
+        (None, Halt),
+        (None, Jump(3)),
+    ]);
+    let cfg = Cfg::from_arena(&arena);
+    assert!(!cfg.contains(4));
+    assert_eq!(cfg.preds(3), &[2]);
+    let dom = Dominators::new(&cfg);
+    assert_eq!(dom.idom(2), Some(1));
+    assert_eq!(dom.idom(3), Some(2));
+    assert!(dom.dominates(1, 3));
+    assert!(!dom.contains(4));
+    assert!(!dom.dominates(4, 3));
+    assert!(!dom.dominates(0, 4));
+    assert_eq!(dom.frontier(2).collect::<Vec<_>>(), [1]);
+    assert_eq!(dom.frontier(1).collect::<Vec<_>>(), [1]);
+}
+
+#[test]
+fn dom_unknown_and_multiple_entries() {
+    // 0 -> 1 -> (unknown); 2 is labeled
+    let mut arena = build(&[(None, Jump(1)), (None, Unknown), (None, Jump(1))]);
+    arena.set_label("other".into(), 2, false).unwrap();
+    let cfg = Cfg::from_arena(&arena);
+    assert_eq!(cfg.entries(), &[0, 2]);
+    // unknown jumps may reach every entry point
+    assert_eq!(cfg.succs(1), &[0, 2]);
+    assert_eq!(cfg.exits(), &[1]);
+    let dom = Dominators::new(&cfg);
+    assert_eq!(dom.idom(0), None);
+    assert_eq!(dom.idom(2), None);
+    assert_eq!(dom.idom(1), None);
+    assert!(!dom.dominates(0, 1));
+
+    let cfg = Cfg::new(&arena, vec![0]);
+    assert!(!cfg.contains(2));
+    let dom = Dominators::new(&cfg);
+    assert_eq!(dom.idom(1), Some(0));
+    assert_eq!(dom.frontier(1).collect::<Vec<_>>(), [0]);
+}
+
+#[test]
+fn pdom_and_control_dependence() {
+    //   0
+    //  / \
+    // 1   2 <-+
+    //  \ / \  |
+    //   3   4-+
+    let arena = build(&[
+        (Some(2), Jump(1)),
+        (None, Jump(3)),
+        (Some(4), Jump(3)),
+        (None, Return),
+        (None, Jump(2)),
+    ]);
+    let cfg = Cfg::from_arena(&arena);
+    let pdom = PostDominators::new(&cfg);
+    assert_eq!(pdom.roots().collect::<Vec<_>>(), [3]);
+    assert_eq!(pdom.ipdom(3), None);
+    assert_eq!(pdom.ipdom(0), Some(3));
+    assert_eq!(pdom.ipdom(1), Some(3));
+    assert_eq!(pdom.ipdom(2), Some(3));
+    assert_eq!(pdom.ipdom(4), Some(2));
+    assert!(pdom.post_dominates(3, 0));
+    assert!(pdom.strictly_post_dominates(2, 4));
+    assert!(!pdom.post_dominates(1, 0));
+
+    let cd = ControlDependence::new(&cfg, &pdom);
+    assert_eq!(cd.deps(1).collect::<Vec<_>>(), [0]);
+    assert_eq!(cd.deps(2).collect::<Vec<_>>(), [0, 2]);
+    assert_eq!(cd.deps(4).collect::<Vec<_>>(), [2]);
+    assert_eq!(cd.deps(3).count(), 0);
+    assert_eq!(cd.deps(0).count(), 0);
+    assert_eq!(cd.dependents(0).collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(cd.dependents(2).collect::<Vec<_>>(), [2, 4]);
+}
+
+#[test]
+fn pdom_infinite_loop() {
+    // 0 -> 1 -> 1 (forever), 0 -> 2 (halt)
+    let arena = build(&[(Some(2), Jump(1)), (None, Jump(1)), (None, Halt)]);
+    let cfg = Cfg::from_arena(&arena);
+    let pdom = PostDominators::new(&cfg);
+    assert!(!pdom.contains(1));
+    assert!(pdom.contains(0));
+    assert!(pdom.contains(2));
+    assert_eq!(pdom.ipdom(1), None);
+    let cd = ControlDependence::new(&cfg, &pdom);
+    assert_eq!(cd.deps(1).count(), 0);
+}
+
+#[test]
+fn loops_nested() {
+    // 0 -> 1 -> 2 -> 3 -> 2, 3 -> 4 -> 1, 4 -> 5
+    let arena = build(&[
+        (None, Jump(1)),
+        (None, Jump(2)),
+        (None, Jump(3)),
+        (Some(2), Jump(4)),
+        (Some(1), Jump(5)),
+        (None, Return),
+    ]);
+    let cfg = Cfg::from_arena(&arena);
+    let dom = Dominators::new(&cfg);
+    let lf = LoopForest::new(&cfg, &dom);
+    assert!(lf.is_reducible());
+    assert_eq!(lf.back_edges(), &[(3, 2), (4, 1)]);
+    assert_eq!(lf.roots(), &[0]);
+
+    let outer = lf.get(0).unwrap();
+    assert_eq!(outer.header, 1);
+    assert_eq!(outer.latches.iter().copied().collect::<Vec<_>>(), [4]);
+    assert_eq!(outer.body.iter().copied().collect::<Vec<_>>(), [1, 2, 3, 4]);
+    assert_eq!(outer.exits.iter().copied().collect::<Vec<_>>(), [5]);
+    assert_eq!(outer.children, [1]);
+    assert_eq!(outer.depth, 1);
+
+    let inner = lf.get(1).unwrap();
+    assert_eq!(inner.header, 2);
+    assert_eq!(inner.body.iter().copied().collect::<Vec<_>>(), [2, 3]);
+    assert_eq!(inner.exits.iter().copied().collect::<Vec<_>>(), [4]);
+    assert_eq!(inner.parent, Some(0));
+    assert_eq!(inner.depth, 2);
+
+    assert_eq!(lf.loop_of_header(2), Some(1));
+    assert_eq!(lf.innermost_loop(3), Some(1));
+    assert_eq!(lf.innermost_loop(4), Some(0));
+    assert_eq!(lf.innermost_loop(5), None);
+    assert_eq!(lf.loop_depth(3), 2);
+    assert_eq!(lf.loop_depth(0), 0);
+}
+
+#[test]
+fn loops_irreducible() {
+    // 0 -> 1, 0 -> 2, 1 <-> 2
+    let arena = build(&[
+        (Some(2), Jump(1)),
+        (None, Jump(2)),
+        (Some(3), Jump(1)),
+        (None, Halt),
+    ]);
+    let cfg = Cfg::from_arena(&arena);
+    let dom = Dominators::new(&cfg);
+    let lf = LoopForest::new(&cfg, &dom);
+    assert!(lf.loops().is_empty());
+    assert!(lf.back_edges().is_empty());
+    assert!(!lf.is_reducible());
+    let irr = lf.irreducible();
+    assert_eq!(irr.len(), 1);
+    assert_eq!(irr[0].body.iter().copied().collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(irr[0].entries.iter().copied().collect::<Vec<_>>(), [1, 2]);
+}
+
+#[test]
+fn loops_irreducible_nested() {
+    // 0 -> 1, loop 1 -> {2, 3}, 2 <-> 3, 3 -> 1, 2 -> 4
+    let arena = build(&[
+        (None, Jump(1)),
+        (Some(2), Jump(3)),
+        (Some(3), Jump(4)),
+        (Some(2), Jump(1)),
+        (None, Halt),
+    ]);
+    let cfg = Cfg::from_arena(&arena);
+    let dom = Dominators::new(&cfg);
+    let lf = LoopForest::new(&cfg, &dom);
+    assert_eq!(lf.back_edges(), [(3, 1)]);
+    assert_eq!(lf.loops().len(), 1);
+    assert_eq!(
+        lf.loops()[0].body.iter().copied().collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+    let irr = lf.irreducible();
+    assert_eq!(irr.len(), 1);
+    assert_eq!(irr[0].body.iter().copied().collect::<Vec<_>>(), [2, 3]);
+    assert_eq!(irr[0].entries.iter().copied().collect::<Vec<_>>(), [2, 3]);
+}
+
+#[test]
+fn call_graph() {
+    // main: 0 -> call 3 -> 1 -> call 4 -> 2
+    // f: 3 -> call 4 -> 5
+    // g: 4 (extern)
+    let call = |callee, continuation| {
+        Unconditional::Call(Call {
+            callee,
+            continuation,
+        })
+    };
+    let mut arena = build(&[
+        (None, call(3, 1)),
+        (None, call(4, 2)),
+        (None, Return),
+        (None, call(4, 5)),
+        (None, Return),
+        (None, Return),
+    ]);
+    arena.bbs_mut().get_mut(&4).unwrap().inner = BasicBlockInner::Placeholder { is_extern: true };
+    arena.set_label("g".into(), 4, false).unwrap();
+    arena.check().unwrap();
+
+    // the callee isn't a successor of the call site
+    let cfg = Cfg::new(&arena, vec![0]);
+    assert_eq!(cfg.rpo(), [0, 1, 2]);
+    assert_eq!(cfg.succs(0), [1]);
+    assert_eq!(cfg.exits(), [2]);
+
+    let cg = CallGraph::new(&arena);
+    assert_eq!(cg.functions().collect::<Vec<_>>(), [0, 3, 4]);
+    assert_eq!(cg.callees(0), [3, 4]);
+    assert_eq!(cg.callees(3), [4]);
+    assert!(cg.callees(4).is_empty());
+    assert_eq!(cg.callers(4), [0, 3]);
+    assert!(cg.callers(0).is_empty());
+    assert_eq!(cg.call_sites(0), [0, 1]);
+    assert_eq!(cg.call_sites(3), [3]);
+    assert!(!cg.contains(1));
+}
+
+#[test]
+fn cfg_call_successors() {
+    // 0: if 2; call 4 then 3; unwind 1
+    let mut arena = build(&[
+        (
+            Some(2),
+            Unconditional::Call(Call {
+                callee: 4,
+                continuation: 3,
+            }),
+        ),
+        (None, Return),
+        (None, Return),
+        (None, Return),
+        (None, Return),
+    ]);
+    if let BasicBlockInner::Concrete { unwind, .. } =
+        &mut arena.bbs_mut().get_mut(&0).unwrap().inner
+    {
+        *unwind = Some(1);
+    }
+    let cfg = Cfg::new(&arena, vec![0]);
+    assert_eq!(cfg.succs(0), [1, 2, 3]);
+    assert_eq!(cfg.rpo().len(), 4);
+    assert!(!cfg.rpo().contains(&4));
+}
+
+fn assert_approx(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+}
+
+#[test]
+fn block_frequency() {
+    // 0 -> 1 <-> 2, 1 -> 3 (taken in 1 of 10 cases)
+    let mut arena = build(&[
+        (None, Jump(1)),
+        (Some(3), Jump(2)),
+        (None, Jump(1)),
+        (None, Return),
+    ]);
+    let freqs = |arena: &TestArena| {
+        let cfg = Cfg::from_arena(arena);
+        let lf = LoopForest::new(&cfg, &Dominators::new(&cfg));
+        BlockFrequency::new(&cfg, &lf, arena)
+    };
+
+    // without weights, the loop is left in 1 of 2 cases
+    let bf = freqs(&arena);
+    assert_approx(bf.frequency(1).unwrap(), 2.0);
+    assert_approx(bf.frequency(3).unwrap(), 1.0);
+
+    arena
+        .set_branch_weights(
+            1,
+            BranchWeights {
+                condjmp: Some((1, 9)),
+                switch: None,
+            },
+        )
+        .unwrap();
+    let bf = freqs(&arena);
+    let expected = [1.0, 10.0, 9.0, 1.0];
+    for (n, &f) in expected.iter().enumerate() {
+        assert_approx(bf.frequency(n).unwrap(), f);
+    }
+    assert_eq!(bf.frequencies().len(), 4);
+    assert_approx(bf.edge_probability(1, 3), 0.1);
+    assert_approx(bf.edge_frequency(2, 1), 9.0);
+    assert_approx(bf.edge_probability(0, 3), 0.0);
+}
+
+#[test]
+fn block_frequency_switch() {
+    let switch = Unconditional::Switch(Switch {
+        cases: vec![1, 2],
+        default: 3,
+    });
+    let mut arena = build(&[(None, switch), (None, Halt), (None, Halt), (None, Halt)]);
+    arena
+        .set_branch_weights(
+            0,
+            BranchWeights {
+                condjmp: None,
+                switch: Some(vec![1, 3, 0]),
+            },
+        )
+        .unwrap();
+    let cfg = Cfg::from_arena(&arena);
+    let lf = LoopForest::new(&cfg, &Dominators::new(&cfg));
+    let bf = BlockFrequency::new(&cfg, &lf, &arena);
+    assert_approx(bf.frequency(1).unwrap(), 0.25);
+    assert_approx(bf.frequency(2).unwrap(), 0.75);
+    assert_approx(bf.frequency(3).unwrap(), 0.0);
+}
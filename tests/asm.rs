@@ -0,0 +1,325 @@
+use core::fmt::{self, Write};
+use yz_basic_block::{
+    asm::{self, ParseError, ParseErrorKind},
+    jump::{Call, ForeachTarget, Switch, Unconditional},
+    Arena, BasicBlockInner, BbId, OffendingIds,
+};
+
+mod common;
+use common::Stmt;
+
+/// `<var> bb<N>`
+#[derive(Debug, PartialEq)]
+struct Cond {
+    var: String,
+    target: BbId,
+}
+
+impl ForeachTarget for Cond {
+    type JumpTarget = BbId;
+
+    fn foreach_target<F>(&self, mut f: F)
+    where
+        F: FnMut(&Self::JumpTarget),
+    {
+        f(&self.target);
+    }
+
+    fn foreach_target_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Self::JumpTarget),
+    {
+        f(&mut self.target);
+    }
+}
+
+impl asm::Print for Stmt {
+    fn print(&self, w: &mut dyn Write) -> fmt::Result {
+        w.write_str(&self.0)
+    }
+}
+
+impl asm::Parse for Stmt {
+    fn parse(s: &str) -> Result<Self, String> {
+        if s.contains('=') {
+            Ok(Stmt(s.to_string()))
+        } else {
+            Err(format!("not an assignment: {}", s))
+        }
+    }
+}
+
+impl asm::Print for Cond {
+    fn print(&self, w: &mut dyn Write) -> fmt::Result {
+        write!(w, "{} bb{}", self.var, self.target)
+    }
+}
+
+impl asm::Parse for Cond {
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut it = s.split_whitespace();
+        let var = it.next().ok_or("missing variable")?.to_string();
+        let target = it
+            .next()
+            .and_then(asm::parse_target)
+            .ok_or("invalid target")?;
+        Ok(Cond { var, target })
+    }
+}
+
+type TestArena = Arena<Stmt, Cond>;
+
+const PROGRAM: &str = "label main
+pub bb0:
+    x = 1
+    if x bb2
+    jmp bb1
+
+bb1:
+    y = x
+    ret
+
+label puts
+label puts2
+extern placeholder bb2
+
+label decl
+placeholder bb3
+";
+
+#[test]
+fn roundtrip() {
+    let arena: TestArena = asm::parse(PROGRAM).unwrap();
+    assert_eq!(arena.len(), 4);
+    assert_eq!(arena.label2bb("main").unwrap().0, 0);
+    assert_eq!(arena.label2bb("puts2").unwrap().0, 2);
+    assert!(arena.bbs()[&0].is_public);
+    match &arena.bbs()[&0].inner {
+        BasicBlockInner::Concrete {
+            statements,
+            condjmp,
+            next,
+            unwind,
+        } => {
+            assert_eq!(statements, &[Stmt("x = 1".to_string())]);
+            assert_eq!(condjmp.as_ref().unwrap().target, 2);
+            assert_eq!(*next, Unconditional::Jump(1));
+            assert_eq!(*unwind, None);
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(
+        arena.bbs()[&2].inner,
+        BasicBlockInner::Placeholder { is_extern: true }
+    );
+    assert_eq!(asm::to_string(&arena), PROGRAM);
+}
+
+#[test]
+fn roundtrip_escaped_statements() {
+    let program = "pub bb0:
+    \\call puts = x
+    \\jmp = 1
+    ret = 2
+    \\# x = 3
+    \\\\ y = 4
+    \\if = 5
+    ret
+";
+    let arena: TestArena = asm::parse(program).unwrap();
+    match &arena.bbs()[&0].inner {
+        BasicBlockInner::Concrete {
+            statements, next, ..
+        } => {
+            let statements: Vec<_> = statements.iter().map(|i| i.0.as_str()).collect();
+            assert_eq!(
+                statements,
+                [
+                    "call puts = x",
+                    "jmp = 1",
+                    "ret = 2",
+                    "# x = 3",
+                    "\\ y = 4",
+                    "if = 5"
+                ]
+            );
+            assert_eq!(*next, Unconditional::Return);
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(asm::to_string(&arena), program);
+}
+
+#[test]
+fn jump_to_label() {
+    let arena: TestArena = asm::parse(
+        "# a comment
+        pub bb5:
+            jmp end
+        label end
+        bb7:
+            halt",
+    )
+    .unwrap();
+    match &arena.bbs()[&5].inner {
+        BasicBlockInner::Concrete { next, .. } => assert_eq!(*next, Unconditional::Jump(7)),
+        _ => unreachable!(),
+    }
+}
+
+fn parse_err(s: &str) -> ParseError {
+    asm::parse::<Stmt, Cond>(s).unwrap_err()
+}
+
+#[test]
+fn parse_errors() {
+    let e = |line, kind| ParseError { line, kind };
+    assert_eq!(parse_err("x = 1"), e(1, ParseErrorKind::ExpectedHeader));
+    assert_eq!(
+        parse_err("placeholder 3"),
+        e(1, ParseErrorKind::InvalidHeader)
+    );
+    assert_eq!(
+        parse_err("extern bb0:\nret"),
+        e(1, ParseErrorKind::InvalidHeader)
+    );
+    assert_eq!(
+        parse_err("bb0:\nhalt\nbb0:\nhalt"),
+        e(3, ParseErrorKind::DuplicateBb(0))
+    );
+    assert_eq!(
+        parse_err("bb0:\nx = 1"),
+        e(2, ParseErrorKind::MissingTerminator)
+    );
+    assert_eq!(
+        parse_err("bb0:\nif x bb0\nx = 1\nhalt"),
+        e(3, ParseErrorKind::ExpectedTerminator)
+    );
+    assert_eq!(
+        parse_err("bb0:\nif x bb0\nif x bb0\nhalt"),
+        e(3, ParseErrorKind::MultipleCondjmps)
+    );
+    assert_eq!(
+        parse_err("bb0:\nfoo\nhalt"),
+        e(
+            2,
+            ParseErrorKind::Statement("not an assignment: foo".into())
+        )
+    );
+    assert_eq!(
+        parse_err("bb0:\njmp nowhere"),
+        e(2, ParseErrorKind::UnknownLabel("nowhere".into()))
+    );
+    assert_eq!(
+        parse_err("bb0:\nhalt\nlabel x"),
+        e(3, ParseErrorKind::DanglingLabel("x".into()))
+    );
+    assert_eq!(
+        parse_err("bb0:\njmp bb1"),
+        e(0, ParseErrorKind::Check(OffendingIds(vec![(0, 1)])))
+    );
+    // placeholders need a label
+    assert_eq!(
+        parse_err("placeholder bb0"),
+        e(0, ParseErrorKind::Check(OffendingIds(vec![(0, 0)])))
+    );
+}
+
+#[test]
+fn switch() {
+    let src = "pub bb0:
+    x = 1
+    switch bb1, end, bb1 else bb0
+
+bb1:
+    ret
+
+label end
+bb2:
+    halt
+";
+    let arena: TestArena = asm::parse(src).unwrap();
+    match &arena.bbs()[&0].inner {
+        BasicBlockInner::Concrete { next, .. } => assert_eq!(
+            *next,
+            Unconditional::Switch(Switch {
+                cases: vec![1, 2, 1],
+                default: 0,
+            })
+        ),
+        _ => unreachable!(),
+    }
+    assert_eq!(asm::to_string(&arena), src.replace("end,", "bb2,"));
+
+    let arena: TestArena = asm::parse("pub bb0:\nswitch else bb0").unwrap();
+    assert_eq!(asm::to_string(&arena), "pub bb0:\n    switch else bb0\n");
+
+    assert_eq!(
+        parse_err("bb0:\nswitch bb0 bb0"),
+        ParseError {
+            line: 2,
+            kind: ParseErrorKind::InvalidTerminator
+        }
+    );
+}
+
+#[test]
+fn indirect_jump_and_call() {
+    let src = "pub bb0:
+    x = 1
+    ijmp bb1, next
+
+bb1:
+    call bb0 then next
+
+bb2:
+    unwind bb1
+    ijmp *
+
+label next
+bb3:
+    ijmp
+";
+    let arena: TestArena = asm::parse(src).unwrap();
+    let nexts: Vec<_> = arena
+        .bbs()
+        .values()
+        .map(|bb| match &bb.inner {
+            BasicBlockInner::Concrete { next, .. } => next.clone(),
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(
+        nexts,
+        [
+            Unconditional::Indirect(Some(vec![1, 3])),
+            Unconditional::Call(Call {
+                callee: 0,
+                continuation: 3
+            }),
+            Unconditional::Indirect(None),
+            Unconditional::Indirect(Some(vec![])),
+        ]
+    );
+    assert_eq!(asm::to_string(&arena), src.replace("next\n\n", "bb3\n\n"));
+
+    match &arena.bbs()[&2].inner {
+        BasicBlockInner::Concrete { unwind, .. } => assert_eq!(*unwind, Some(1)),
+        _ => unreachable!(),
+    }
+
+    assert_eq!(
+        parse_err("bb0:\nunwind bb0\nunwind bb0\nret"),
+        ParseError {
+            line: 3,
+            kind: ParseErrorKind::MultipleUnwindTargets
+        }
+    );
+    assert_eq!(
+        parse_err("bb0:\ncall bb0"),
+        ParseError {
+            line: 2,
+            kind: ParseErrorKind::InvalidTerminator
+        }
+    );
+}
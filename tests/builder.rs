@@ -0,0 +1,68 @@
+use yz_basic_block::{
+    jump::{Dummy, Unconditional},
+    ArenaBuilder, BasicBlock, BasicBlockInner, BbId, BuildError, SetBbLabelError,
+};
+
+mod common;
+use common::{bb, Cond, TestBb};
+
+type TestBuilder = ArenaBuilder<Dummy<BbId>, Cond>;
+
+fn concrete(condjmp: Option<BbId>, next: Unconditional<BbId>) -> TestBb {
+    bb(false, condjmp, next)
+}
+#[test]
+fn forward_refs() {
+    let mut b = TestBuilder::new();
+    let head = b.label("head".into());
+    let exit = b.reserve();
+    let body = b.push(concrete(None, Unconditional::Jump(head)));
+    b.define(head, concrete(Some(exit), Unconditional::Jump(body)))
+        .unwrap();
+    assert!(b.define(head, concrete(None, Unconditional::Halt)).is_err());
+    b.define(exit, concrete(None, Unconditional::Return))
+        .unwrap();
+    b.set_label("head".into(), head).unwrap();
+    assert_eq!(
+        b.set_label("head".into(), exit),
+        Err(SetBbLabelError::LabelAlreadyExists { orig_target: head })
+    );
+
+    let arena = b.finish().unwrap();
+    assert_eq!(arena.len(), 3);
+    assert_eq!(arena.label2bb("head").unwrap().0, head);
+    let preds = arena.predecessors(head).unwrap();
+    assert!(preds.bbs.contains(&body));
+    assert!(preds.labels.contains("head"));
+    arena.check().unwrap();
+}
+
+#[test]
+fn unresolved() {
+    let mut b = TestBuilder::new();
+    let missing = b.label("missing".into());
+    let reserved = b.reserve();
+    let a = b.push(concrete(Some(reserved), Unconditional::Jump(missing)));
+    let c = b.push(concrete(None, Unconditional::Jump(missing)));
+    let ph = b.push(BasicBlock {
+        inner: BasicBlockInner::Placeholder { is_extern: true },
+        is_public: false,
+    });
+
+    assert_eq!(
+        b.finish().unwrap_err(),
+        vec![
+            BuildError::Undefined {
+                bbid: missing,
+                labels: vec!["missing".to_string()],
+                referenced_by: vec![a, c],
+            },
+            BuildError::Undefined {
+                bbid: reserved,
+                labels: vec![],
+                referenced_by: vec![a],
+            },
+            BuildError::UnlabeledPlaceholder(ph),
+        ]
+    );
+}
@@ -0,0 +1,79 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use yz_basic_block::{
+    jump::{Dummy, ForeachTarget, Unconditional},
+    Arena, BasicBlock, BasicBlockInner, BbId,
+};
+
+pub mod vars;
+
+/// opaque statement without jump targets, e.g. `x = 1`
+#[derive(Debug, PartialEq)]
+pub struct Stmt(pub String);
+
+impl ForeachTarget for Stmt {
+    type JumpTarget = BbId;
+
+    fn foreach_target<F>(&self, _f: F)
+    where
+        F: FnMut(&Self::JumpTarget),
+    {
+    }
+
+    fn foreach_target_mut<F>(&mut self, _f: F)
+    where
+        F: FnMut(&mut Self::JumpTarget),
+    {
+    }
+}
+
+/// conditional jump to a single target
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cond<T = BbId>(pub T);
+
+impl<T> ForeachTarget for Cond<T> {
+    type JumpTarget = T;
+
+    fn foreach_target<F>(&self, mut f: F)
+    where
+        F: FnMut(&Self::JumpTarget),
+    {
+        f(&self.0);
+    }
+
+    fn foreach_target_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Self::JumpTarget),
+    {
+        f(&mut self.0);
+    }
+}
+
+pub type TestArena<T = BbId> = Arena<Dummy<T>, Cond<T>, T>;
+pub type TestBb<T = BbId> = BasicBlock<Dummy<T>, Cond<T>, T>;
+
+/// concrete BB without statements
+pub fn bb<T>(is_public: bool, condjmp: Option<T>, next: Unconditional<T>) -> TestBb<T> {
+    BasicBlock {
+        inner: BasicBlockInner::Concrete {
+            statements: Vec::new(),
+            condjmp: condjmp.map(Cond),
+            next,
+            unwind: None,
+        },
+        is_public,
+    }
+}
+
+/// builds an arena where BB $i has the conditional jump and terminator
+/// `bbs[$i]`, and BB 0 is the only public one.
+pub fn build(bbs: &[(Option<BbId>, Unconditional<BbId>)]) -> TestArena {
+    let mut arena = TestArena::new();
+    for (n, (condjmp, next)) in bbs.iter().cloned().enumerate() {
+        arena.bbs_mut().insert(n, bb(n == 0, condjmp, next));
+    }
+    arena.check().unwrap();
+    arena.update_predecessors();
+    arena
+}
@@ -0,0 +1,187 @@
+//! Statements on named variables, for the SSA and liveness tests.
+
+use yz_basic_block::{
+    jump::{ForeachTarget, Unconditional},
+    ssa::MakeCopy,
+    var::ForeachVar,
+    Arena, BasicBlock, BasicBlockInner, BbId,
+};
+
+/// `dst = op(srcs...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assign {
+    pub dst: String,
+    pub srcs: Vec<String>,
+}
+
+/// `if var { goto target }`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Branch {
+    pub var: String,
+    pub target: BbId,
+}
+
+impl ForeachTarget for Assign {
+    type JumpTarget = BbId;
+
+    fn foreach_target<F>(&self, _f: F)
+    where
+        F: FnMut(&Self::JumpTarget),
+    {
+    }
+
+    fn foreach_target_mut<F>(&mut self, _f: F)
+    where
+        F: FnMut(&mut Self::JumpTarget),
+    {
+    }
+}
+
+impl ForeachVar for Assign {
+    type Var = String;
+
+    fn foreach_use<F>(&self, f: F)
+    where
+        F: FnMut(&Self::Var),
+    {
+        self.srcs.iter().for_each(f);
+    }
+
+    fn foreach_def<F>(&self, mut f: F)
+    where
+        F: FnMut(&Self::Var),
+    {
+        f(&self.dst);
+    }
+
+    fn foreach_use_mut<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut Self::Var),
+    {
+        self.srcs.iter_mut().for_each(f);
+    }
+
+    fn foreach_def_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Self::Var),
+    {
+        f(&mut self.dst);
+    }
+}
+
+impl MakeCopy<String> for Assign {
+    fn make_copy(dst: String, src: String) -> Self {
+        Assign {
+            dst,
+            srcs: vec![src],
+        }
+    }
+}
+
+impl ForeachTarget for Branch {
+    type JumpTarget = BbId;
+
+    fn foreach_target<F>(&self, mut f: F)
+    where
+        F: FnMut(&Self::JumpTarget),
+    {
+        f(&self.target);
+    }
+
+    fn foreach_target_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Self::JumpTarget),
+    {
+        f(&mut self.target);
+    }
+}
+
+impl ForeachVar for Branch {
+    type Var = String;
+
+    fn foreach_use<F>(&self, mut f: F)
+    where
+        F: FnMut(&Self::Var),
+    {
+        f(&self.var);
+    }
+
+    fn foreach_def<F>(&self, _f: F)
+    where
+        F: FnMut(&Self::Var),
+    {
+    }
+
+    fn foreach_use_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Self::Var),
+    {
+        f(&mut self.var);
+    }
+
+    fn foreach_def_mut<F>(&mut self, _f: F)
+    where
+        F: FnMut(&mut Self::Var),
+    {
+    }
+}
+
+pub type TestArena = Arena<Assign, Branch>;
+pub type TestBb<'a> = (Vec<Assign>, Option<(&'a str, BbId)>, Unconditional<BbId>);
+
+pub fn assign(dst: &str, srcs: &[&str]) -> Assign {
+    Assign {
+        dst: dst.to_string(),
+        srcs: srcs.iter().map(|i| i.to_string()).collect(),
+    }
+}
+
+pub fn build(bbs: Vec<TestBb<'_>>) -> TestArena {
+    let mut arena = TestArena::new();
+    for (n, (statements, cond, next)) in bbs.into_iter().enumerate() {
+        arena.bbs_mut().insert(
+            n,
+            BasicBlock {
+                inner: BasicBlockInner::Concrete {
+                    statements,
+                    condjmp: cond.map(|(var, target)| Branch {
+                        var: var.to_string(),
+                        target,
+                    }),
+                    next,
+                    unwind: None,
+                },
+                is_public: n == 0,
+            },
+        );
+    }
+    arena.check().unwrap();
+    arena.update_predecessors();
+    arena
+}
+
+pub fn fresh_gen() -> impl FnMut(&String) -> String {
+    let mut cnt = 0;
+    move |v| {
+        cnt += 1;
+        format!("{}.{}", v.split('.').next().unwrap(), cnt)
+    }
+}
+
+pub fn statements(arena: &TestArena, bbid: BbId) -> &[Assign] {
+    match &arena.bbs()[&bbid].inner {
+        BasicBlockInner::Concrete { statements, .. } => statements,
+        _ => unreachable!(),
+    }
+}
+
+/// sets the landing pad of the given BB
+pub fn set_unwind(arena: &mut TestArena, bbid: BbId, pad: BbId) {
+    if let BasicBlockInner::Concrete { unwind, .. } =
+        &mut arena.bbs_mut().get_mut(&bbid).unwrap().inner
+    {
+        *unwind = Some(pad);
+    }
+    arena.check().unwrap();
+    arena.update_predecessors();
+}
@@ -0,0 +1,116 @@
+use yz_basic_block::{
+    jump::{ForeachTarget, Unconditional},
+    BbId, CompactOrder,
+};
+
+mod common;
+use common::{bb, TestArena};
+
+/// 1: ret
+/// 3: jmp 1, label "b"
+/// 5: if 3, jmp 1, pub, label "a"
+/// 6: halt (unreachable)
+fn build() -> TestArena {
+    let mut arena = TestArena::new();
+    for _ in 0..7 {
+        arena.reserve();
+    }
+    for i in [0, 2, 4] {
+        arena.remove(i).unwrap().unwrap();
+    }
+    arena
+        .fill(1, bb(false, None, Unconditional::Return))
+        .unwrap();
+    arena
+        .fill(3, bb(false, None, Unconditional::Jump(1)))
+        .unwrap();
+    arena
+        .fill(5, bb(true, Some(3), Unconditional::Jump(1)))
+        .unwrap();
+    arena.fill(6, bb(false, None, Unconditional::Halt)).unwrap();
+    arena.set_label("b".into(), 3, false).unwrap();
+    arena.set_label("a".into(), 5, false).unwrap();
+    arena.check().unwrap();
+    arena
+}
+
+fn targets(arena: &TestArena) -> Vec<(BbId, Vec<BbId>)> {
+    arena
+        .bbs()
+        .iter()
+        .map(|(&n, bb)| {
+            let mut ts = Vec::new();
+            bb.foreach_target(|&t| ts.push(t));
+            (n, ts)
+        })
+        .collect()
+}
+
+#[test]
+fn insertion() {
+    let mut arena = build();
+    let map = arena.compact(CompactOrder::Insertion);
+    assert_eq!(
+        map.into_iter().collect::<Vec<_>>(),
+        [(1, 0), (3, 1), (5, 2), (6, 3)]
+    );
+    assert_eq!(
+        targets(&arena),
+        [(0, vec![]), (1, vec![0]), (2, vec![1, 0]), (3, vec![])]
+    );
+    assert_eq!(arena.labels()["a"], 2);
+    assert_eq!(arena.labels()["b"], 1);
+    assert!(arena.predecessors(0).unwrap().bbs.contains(&2));
+    arena.check().unwrap();
+
+    // already compact
+    let map = arena.compact(CompactOrder::Insertion);
+    assert!(map.iter().all(|(a, b)| a == b));
+    // new BBs get appended
+    assert_eq!(arena.reserve(), 4);
+}
+
+#[test]
+fn rpo() {
+    let mut arena = build();
+    let map = arena.compact(CompactOrder::ReversePostorder);
+    assert_eq!(map[&5], 0);
+    assert_eq!(map[&3], 1);
+    assert_eq!(map[&1], 2);
+    assert_eq!(map[&6], 3);
+    assert_eq!(arena.labels()["a"], 0);
+    assert_eq!(targets(&arena)[0], (0, vec![1, 2]));
+    arena.check().unwrap();
+}
+
+#[test]
+fn labels() {
+    let mut arena = build();
+    let map = arena.compact(CompactOrder::Labels);
+    assert_eq!(
+        map.into_iter().collect::<Vec<_>>(),
+        [(1, 2), (3, 1), (5, 0), (6, 3)]
+    );
+    arena.check().unwrap();
+
+    // equivalent arenas end up identical
+    let mut other = TestArena::new();
+    let [halt, ret, b, a] = [(); 4].map(|()| other.reserve());
+    other
+        .fill(halt, bb(false, None, Unconditional::Halt))
+        .unwrap();
+    other
+        .fill(ret, bb(false, None, Unconditional::Return))
+        .unwrap();
+    other
+        .fill(b, bb(false, None, Unconditional::Jump(ret)))
+        .unwrap();
+    other
+        .fill(a, bb(true, Some(b), Unconditional::Jump(ret)))
+        .unwrap();
+    other.set_label("a".into(), a, false).unwrap();
+    other.set_label("b".into(), b, false).unwrap();
+    other.compact(CompactOrder::Labels);
+    assert_eq!(targets(&arena), targets(&other));
+    assert_eq!(arena.labels(), other.labels());
+}
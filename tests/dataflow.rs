@@ -0,0 +1,198 @@
+use std::collections::BTreeSet;
+use yz_basic_block::{
+    analysis::{
+        dataflow::{self, Analysis, Direction, Transfer},
+        Cfg,
+    },
+    jump::{Dummy, ForeachTarget, Unconditional},
+    Arena, BasicBlock, BasicBlockInner, BbId,
+};
+
+/// test statement which defines a single variable
+#[derive(Debug)]
+struct Def(char);
+
+impl ForeachTarget for Def {
+    type JumpTarget = BbId;
+
+    fn foreach_target<F>(&self, _f: F)
+    where
+        F: FnMut(&Self::JumpTarget),
+    {
+    }
+
+    fn foreach_target_mut<F>(&mut self, _f: F)
+    where
+        F: FnMut(&mut Self::JumpTarget),
+    {
+    }
+}
+
+/// collects all variables which may be defined
+impl Transfer<BTreeSet<char>> for Def {
+    fn transfer(&self, fact: &mut BTreeSet<char>) {
+        fact.insert(self.0);
+    }
+}
+
+struct MayDefined;
+
+impl Analysis<Def, Dummy<BbId>> for MayDefined {
+    type Fact = BTreeSet<char>;
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn transfer_bb(&self, _: BbId, bb: &BasicBlock<Def, Dummy<BbId>, BbId>, fact: &mut Self::Fact) {
+        dataflow::transfer_statements(Self::DIRECTION, bb, fact);
+    }
+}
+
+/// counts the maximum number of BBs until an exit is reached (saturating at 10)
+struct Distance;
+
+#[derive(Clone, Debug, PartialEq)]
+struct MaxDist(u8);
+
+impl dataflow::Lattice for MaxDist {
+    fn bottom() -> Self {
+        MaxDist(0)
+    }
+
+    fn join(&mut self, other: &Self) -> bool {
+        if other.0 > self.0 {
+            self.0 = other.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Analysis<Def, Dummy<BbId>> for Distance {
+    type Fact = MaxDist;
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn transfer_bb(&self, _: BbId, _: &BasicBlock<Def, Dummy<BbId>, BbId>, fact: &mut MaxDist) {
+        fact.0 = core::cmp::min(fact.0 + 1, 10);
+    }
+}
+
+fn bb(statements: Vec<Def>, next: Unconditional<BbId>) -> BasicBlock<Def, Dummy<BbId>, BbId> {
+    BasicBlock {
+        inner: BasicBlockInner::Concrete {
+            statements,
+            condjmp: None,
+            next,
+            unwind: None,
+        },
+        is_public: false,
+    }
+}
+
+#[test]
+fn forward_and_backward() {
+    let mut arena = Arena::<Def, Dummy<BbId>>::new();
+    let exit = arena
+        .push(bb(vec![Def('c')], Unconditional::Return))
+        .unwrap();
+    let mid = arena
+        .push(bb(vec![Def('b')], Unconditional::Jump(exit)))
+        .unwrap();
+    let mut head = bb(vec![Def('a')], Unconditional::Jump(mid));
+    head.is_public = true;
+    let head = arena.push(head).unwrap();
+
+    let cfg = Cfg::from_arena(&arena);
+    let res = dataflow::solve(&cfg, &arena, &MayDefined);
+    let set = |s: &str| s.chars().collect::<BTreeSet<_>>();
+    assert_eq!(res.get_in(head), Some(&set("")));
+    assert_eq!(res.get_out(head), Some(&set("a")));
+    assert_eq!(res.get_in(mid), Some(&set("a")));
+    assert_eq!(res.get_out(exit), Some(&set("abc")));
+
+    let res = dataflow::solve(&cfg, &arena, &Distance);
+    assert_eq!(res.get_out(exit), Some(&MaxDist(0)));
+    assert_eq!(res.get_in(exit), Some(&MaxDist(1)));
+    assert_eq!(res.get_in(head), Some(&MaxDist(3)));
+}
+
+#[test]
+fn fixpoint_in_loop() {
+    let mut arena = Arena::<Def, Dummy<BbId>>::new();
+    let mut head = bb(vec![Def('x'), Def('z')], Unconditional::Jump(1));
+    head.is_public = true;
+    arena.bbs_mut().insert(0, head);
+    arena
+        .bbs_mut()
+        .insert(1, bb(vec![Def('y')], Unconditional::Jump(0)));
+    arena.check().unwrap();
+
+    let cfg = Cfg::from_arena(&arena);
+    let res = dataflow::solve(&cfg, &arena, &MayDefined);
+    let set = |s: &str| s.chars().collect::<BTreeSet<_>>();
+    assert_eq!(res.get_in(0), Some(&set("xyz")));
+    assert_eq!(res.get_out(0), Some(&set("xyz")));
+    assert_eq!(res.get_in(1), Some(&set("xyz")));
+
+    // without any exit, the distance saturates inside of the loop
+    let res = dataflow::solve(&cfg, &arena, &Distance);
+    assert_eq!(res.get_in(0), Some(&MaxDist(10)));
+    assert_eq!(res.get_out(1), Some(&MaxDist(10)));
+}
+
+/// like `MayDefined`, but also collects the definitions
+/// which happen before the BB unwinds
+struct MayDefinedUnwind;
+
+impl Analysis<Def, Dummy<BbId>> for MayDefinedUnwind {
+    type Fact = BTreeSet<char>;
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn transfer_bb(&self, _: BbId, bb: &BasicBlock<Def, Dummy<BbId>, BbId>, fact: &mut Self::Fact) {
+        dataflow::transfer_statements(Self::DIRECTION, bb, fact);
+    }
+
+    fn transfer_unwind(
+        &self,
+        _: BbId,
+        bb: &BasicBlock<Def, Dummy<BbId>, BbId>,
+        fact: &mut Self::Fact,
+    ) {
+        dataflow::transfer_unwind_statements(Self::DIRECTION, bb, fact);
+    }
+}
+
+#[test]
+fn unwind_edges() {
+    // 0: a; b; return; unwind 1
+    // 1: c; goto 2
+    // 2: return
+    let mut arena = Arena::<Def, Dummy<BbId>>::new();
+    let mut head = bb(vec![Def('a'), Def('b')], Unconditional::Return);
+    head.is_public = true;
+    if let BasicBlockInner::Concrete { unwind, .. } = &mut head.inner {
+        *unwind = Some(1);
+    }
+    arena.bbs_mut().insert(0, head);
+    arena
+        .bbs_mut()
+        .insert(1, bb(vec![Def('c')], Unconditional::Jump(2)));
+    arena.bbs_mut().insert(2, bb(vec![], Unconditional::Return));
+    arena.check().unwrap();
+
+    let cfg = Cfg::from_arena(&arena);
+    let set = |s: &str| s.chars().collect::<BTreeSet<_>>();
+    // by default, the landing pad gets the input of the unwinding BB
+    let res = dataflow::solve(&cfg, &arena, &MayDefined);
+    assert_eq!(res.get_out(0), Some(&set("ab")));
+    assert_eq!(res.get_in(1), Some(&set("")));
+    // ... or the facts right before every statement
+    let res = dataflow::solve(&cfg, &arena, &MayDefinedUnwind);
+    assert_eq!(res.get_in(1), Some(&set("a")));
+    assert_eq!(res.get_out(1), Some(&set("ac")));
+
+    // the unwind edge doesn't contribute to the exit fact
+    let res = dataflow::solve(&cfg, &arena, &Distance);
+    assert_eq!(res.get_out(0), Some(&MaxDist(0)));
+    assert_eq!(res.get_in(1), Some(&MaxDist(2)));
+    assert_eq!(res.get_in(0), Some(&MaxDist(2)));
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "dot")]
+
+use core::fmt::{self, Write};
+use yz_basic_block::{
+    jump::Unconditional, Arena, BasicBlock, BasicBlockInner, DebugFormatter, DotFormatter,
+};
+
+mod common;
+use common::{Cond, Stmt};
+
+struct PlainFormatter;
+
+impl DotFormatter<Stmt, Cond> for PlainFormatter {
+    fn fmt_statement(&self, stmt: &Stmt, w: &mut dyn Write) -> fmt::Result {
+        w.write_str(&stmt.0)
+    }
+
+    fn fmt_condjmp(&self, condjmp: &Cond, w: &mut dyn Write) -> fmt::Result {
+        write!(w, "goto bb{}", condjmp.0)
+    }
+}
+
+#[test]
+fn dot_export() {
+    let mut arena = Arena::<Stmt, Cond>::new();
+    let ext = arena
+        .push(BasicBlock {
+            inner: BasicBlockInner::Placeholder { is_extern: true },
+            is_public: false,
+        })
+        .unwrap();
+    arena.set_label("puts".into(), ext, false).unwrap();
+    let ret = arena
+        .push(BasicBlock {
+            inner: BasicBlockInner::Concrete {
+                statements: vec![],
+                condjmp: None,
+                next: Unconditional::Return,
+                unwind: None,
+            },
+            is_public: false,
+        })
+        .unwrap();
+    let main = arena
+        .push(BasicBlock {
+            inner: BasicBlockInner::Concrete {
+                statements: vec![Stmt("x = \"a\"".to_string())],
+                condjmp: Some(Cond(ext)),
+                next: Unconditional::Jump(ret),
+                unwind: None,
+            },
+            is_public: true,
+        })
+        .unwrap();
+    arena.set_label("main".into(), main, false).unwrap();
+
+    let dot = arena.to_dot();
+    assert!(dot.starts_with("digraph arena {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains("bb0 [label=\"bb0\\lputs:\\lextern placeholder\\l\", style=dashed];"));
+    assert!(dot.contains("bb1 [label=\"bb1\\l  ret\\l\"];"));
+    assert!(dot.contains(
+        "bb2 [label=\"bb2 (pub)\\lmain:\\l  (1 statements)\\l  jmp bb1\\l\", penwidth=2];"
+    ));
+    assert!(dot.contains("bb2 -> bb0 [style=dashed, label=\"if\"];"));
+    assert!(dot.contains("bb2 -> bb1 [style=solid];"));
+
+    let dot = arena.to_dot_with(&DebugFormatter);
+    assert!(dot.contains("\\l  Stmt(\\\"x = \\\\\\\"a\\\\\\\"\\\")\\l  if Cond(0)\\l"));
+
+    let dot = arena.to_dot_with(&PlainFormatter);
+    assert!(dot.contains("main:\\l  x = \\\"a\\\"\\l  if goto bb0\\l  jmp bb1\\l"));
+}
@@ -0,0 +1,87 @@
+use yz_basic_block::{jump::Unconditional, BasicBlockInner, BbId, OffendingIds};
+
+mod common;
+use common::{bb, Cond, TestArena, TestBb};
+
+fn build() -> (TestArena, BbId, BbId) {
+    let mut arena = TestArena::new();
+    let mut push = |next| arena.push(bb(true, None, next)).unwrap();
+    let a = push(Unconditional::Halt);
+    let b = push(Unconditional::Jump(a));
+    (arena, a, b)
+}
+
+fn next_of(arena: &TestArena, bbid: BbId) -> Unconditional<BbId> {
+    match &arena.bbs()[&bbid].inner {
+        BasicBlockInner::Concrete { next, .. } => next.clone(),
+        _ => unreachable!(),
+    }
+}
+
+fn set_next(bb: &mut TestBb, x: Unconditional<BbId>) {
+    if let BasicBlockInner::Concrete { next, .. } = &mut bb.inner {
+        *next = x;
+    }
+}
+
+#[test]
+fn bb_mut() {
+    let (mut arena, a, b) = build();
+
+    // valid edit, committed on drop
+    {
+        let mut bb = arena.bb_mut(a).unwrap();
+        assert_eq!(bb.id(), a);
+        set_next(&mut bb, Unconditional::Jump(b));
+    }
+    assert_eq!(next_of(&arena, a), Unconditional::Jump(b));
+    assert!(arena.predecessors(b).unwrap().bbs.contains(&a));
+
+    // invalid edit, rolled back
+    let mut bb = arena.bb_mut(b).unwrap();
+    set_next(&mut bb, Unconditional::Jump(42));
+    assert_eq!(bb.commit(), Err(OffendingIds(vec![(b, 42)])));
+    assert_eq!(next_of(&arena, b), Unconditional::Jump(a));
+
+    // invalid edit, rolled back on drop
+    {
+        let mut bb = arena.bb_mut(b).unwrap();
+        bb.inner = BasicBlockInner::Placeholder { is_extern: true };
+    }
+    assert!(arena.bbs()[&b].inner.is_concrete());
+
+    // explicit rollback
+    let mut bb = arena.bb_mut(b).unwrap();
+    set_next(&mut bb, Unconditional::Return);
+    bb.rollback();
+    assert_eq!(next_of(&arena, b), Unconditional::Jump(a));
+
+    assert!(arena.bb_mut(42).is_none());
+    arena.check().unwrap();
+}
+
+#[test]
+fn editor() {
+    let (mut arena, a, b) = build();
+
+    let mut ed = arena.edit();
+    set_next(ed.get_mut(a).unwrap(), Unconditional::Jump(b));
+    set_next(ed.get_mut(b).unwrap(), Unconditional::Jump(7));
+    if let BasicBlockInner::Concrete { condjmp, .. } = &mut ed.get_mut(a).unwrap().inner {
+        *condjmp = Some(Cond(8));
+    }
+    assert_eq!(ed.touched().collect::<Vec<_>>(), [a, b]);
+    assert_eq!(ed.commit(), Err(OffendingIds(vec![(a, 8), (b, 7)])));
+    assert_eq!(next_of(&arena, a), Unconditional::Halt);
+    assert_eq!(next_of(&arena, b), Unconditional::Jump(a));
+
+    let mut ed = arena.edit();
+    set_next(ed.get_mut(a).unwrap(), Unconditional::Jump(b));
+    set_next(ed.get_mut(b).unwrap(), Unconditional::Return);
+    ed.commit().unwrap();
+    let p = arena.predecessors(a).unwrap();
+    assert!(p.bbs.is_empty());
+    let p = arena.predecessors(b).unwrap();
+    assert_eq!(p.bbs.iter().collect::<Vec<_>>(), [&a]);
+    arena.check().unwrap();
+}
@@ -0,0 +1,134 @@
+use yz_basic_block::{jump::Unconditional, BasicBlock, BasicBlockInner, BbId, LinkError};
+
+mod common;
+use common::{bb, TestArena};
+
+fn placeholder(arena: &mut TestArena, label: &'static str, is_extern: bool) -> BbId {
+    let id = arena
+        .push(BasicBlock {
+            inner: BasicBlockInner::Placeholder { is_extern },
+            is_public: false,
+        })
+        .unwrap();
+    arena.set_label(label.into(), id, false).unwrap();
+    id
+}
+
+fn concrete(
+    arena: &mut TestArena,
+    label: &'static str,
+    is_public: bool,
+    condjmp: Option<BbId>,
+    next: Unconditional<BbId>,
+) -> BbId {
+    let id = arena.push(bb(is_public, condjmp, next)).unwrap();
+    arena.set_label(label.into(), id, false).unwrap();
+    id
+}
+
+#[test]
+fn link_resolves_placeholders() {
+    let mut a = TestArena::new();
+    let a_foo = placeholder(&mut a, "foo", false);
+    let a_puts = placeholder(&mut a, "puts", true);
+    let a_main = concrete(
+        &mut a,
+        "main",
+        true,
+        Some(a_puts),
+        Unconditional::Jump(a_foo),
+    );
+
+    let mut b = TestArena::new();
+    let b_puts = placeholder(&mut b, "puts", true);
+    let b_local = concrete(&mut b, "local", false, None, Unconditional::Return);
+    let b_foo = concrete(
+        &mut b,
+        "foo",
+        true,
+        Some(b_puts),
+        Unconditional::Jump(b_local),
+    );
+
+    let maps = a.link(vec![b]).unwrap();
+    assert_eq!(maps.len(), 2);
+    assert!(a.check().is_ok());
+    // main, puts, local, foo
+    assert_eq!(a.len(), 4);
+    assert_eq!(maps[0][&a_main], a_main);
+    assert_eq!(maps[0][&a_puts], a_puts);
+    assert_eq!(maps[0][&a_foo], maps[1][&b_foo]);
+    assert_eq!(maps[1][&b_puts], a_puts);
+
+    let foo = maps[1][&b_foo];
+    assert_eq!(a.label2bb("foo").unwrap().0, foo);
+    assert_eq!(a.label2bb("puts").unwrap().0, a_puts);
+    assert_eq!(a.label2bb("local").unwrap().0, maps[1][&b_local]);
+    match &a.bbs()[&a_main].inner {
+        BasicBlockInner::Concrete { condjmp, next, .. } => {
+            assert_eq!(condjmp.as_ref().unwrap().0, a_puts);
+            assert_eq!(*next, Unconditional::Jump(foo));
+        }
+        _ => unreachable!(),
+    }
+    match &a.bbs()[&foo].inner {
+        BasicBlockInner::Concrete { condjmp, next, .. } => {
+            assert_eq!(condjmp.as_ref().unwrap().0, a_puts);
+            assert_eq!(*next, Unconditional::Jump(maps[1][&b_local]));
+        }
+        _ => unreachable!(),
+    }
+    assert!(a.bbs()[&a_puts].inner.is_placeholder());
+    assert!(a.predecessors(a_puts).unwrap().bbs.contains(&foo));
+}
+
+#[test]
+fn link_errors() {
+    let mut a = TestArena::new();
+    placeholder(&mut a, "bar", false);
+    concrete(&mut a, "foo", true, None, Unconditional::Halt);
+    let mut b = TestArena::new();
+    concrete(&mut b, "foo", true, None, Unconditional::Halt);
+    let mut c = TestArena::new();
+    concrete(&mut c, "bar", false, None, Unconditional::Halt);
+
+    let (others, errs) = a.link(vec![b, c]).unwrap_err();
+    assert_eq!(errs.len(), 2);
+    assert!(errs.contains(&LinkError::DuplicateSymbol {
+        label: "bar".into(),
+        bbs: vec![(0, 0), (2, 0)],
+    }));
+    assert!(errs.contains(&LinkError::DuplicateSymbol {
+        label: "foo".into(),
+        bbs: vec![(0, 1), (1, 0)],
+    }));
+    // the arenas are left untouched
+    assert_eq!(a.len(), 2);
+    assert_eq!(others.len(), 2);
+    assert_eq!(others[1].label2bb("bar").unwrap().0, 0);
+
+    let (_, errs) = a.link(vec![]).unwrap_err();
+    assert_eq!(
+        errs,
+        [LinkError::UnresolvedSymbol {
+            labels: vec!["bar".into()],
+            bbs: vec![(0, 0)],
+        }]
+    );
+}
+
+#[test]
+fn link_dangling_label() {
+    let mut a = TestArena::new();
+    let main = concrete(&mut a, "main", true, None, Unconditional::Halt);
+    let gone = concrete(&mut a, "gone", false, None, Unconditional::Return);
+    a.bbs_mut().remove(&gone);
+    let mut b = TestArena::new();
+    concrete(&mut b, "foo", true, None, Unconditional::Halt);
+
+    a.link(vec![b]).unwrap();
+    assert!(a.check().is_ok());
+    assert_eq!(a.label2bb("main").unwrap().0, main);
+    assert!(a.labels().get("gone").is_none());
+    assert!(a.label2bb("foo").is_some());
+}
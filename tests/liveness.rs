@@ -0,0 +1,92 @@
+use std::collections::BTreeSet;
+use yz_basic_block::{
+    analysis::{Cfg, Liveness},
+    jump::Unconditional,
+};
+
+mod common;
+use common::vars::{assign, build, set_unwind};
+
+fn set(vars: &[&str]) -> BTreeSet<String> {
+    vars.iter().map(|i| i.to_string()).collect()
+}
+
+#[test]
+fn liveness_loop() {
+    // 0: i = ; n = ;            goto 1
+    // 1: c = i n; if c goto 3;  goto 2
+    // 2: i = i;                 goto 1
+    // 3: r = n;                 return
+    let arena = build(vec![
+        (
+            vec![assign("i", &[]), assign("n", &[])],
+            None,
+            Unconditional::Jump(1),
+        ),
+        (
+            vec![assign("c", &["i", "n"])],
+            Some(("c", 3)),
+            Unconditional::Jump(2),
+        ),
+        (vec![assign("i", &["i"])], None, Unconditional::Jump(1)),
+        (
+            vec![assign("r", &["n"]), assign("u", &[])],
+            None,
+            Unconditional::Return,
+        ),
+    ]);
+
+    let cfg = Cfg::from_arena(&arena);
+    let live = Liveness::new(&cfg, &arena);
+    assert_eq!(live.live_in(0), Some(&set(&[])));
+    assert_eq!(live.live_out(0), Some(&set(&["i", "n"])));
+    assert_eq!(live.live_in(1), Some(&set(&["i", "n"])));
+    assert_eq!(live.live_out(1), Some(&set(&["i", "n"])));
+    assert_eq!(live.live_in(2), Some(&set(&["i", "n"])));
+    assert_eq!(live.live_in(3), Some(&set(&["n"])));
+    assert_eq!(live.live_out(3), Some(&set(&[])));
+
+    let mut dead = Vec::new();
+    live.foreach_live_after(3, &arena.bbs()[&3], |n, stmt, live| {
+        if !live.contains(&stmt.dst) {
+            dead.push(n);
+        }
+    });
+    assert_eq!(dead, [1, 0]);
+
+    let live = Liveness::with_live_at_exit(&cfg, &arena, &set(&["r"]));
+    assert_eq!(live.live_out(3), Some(&set(&["r"])));
+    assert_eq!(live.live_in(3), Some(&set(&["n"])));
+}
+
+#[test]
+fn liveness_unwind() {
+    // 0: t = q; x = t; return; unwind 1
+    // 1: r = x; return
+    let mut arena = build(vec![
+        (
+            vec![assign("t", &["q"]), assign("x", &["t"])],
+            None,
+            Unconditional::Return,
+        ),
+        (vec![assign("r", &["x"])], None, Unconditional::Return),
+    ]);
+    set_unwind(&mut arena, 0, 1);
+
+    let cfg = Cfg::from_arena(&arena);
+    assert_eq!(cfg.succs(0), [1]);
+    assert_eq!(cfg.unwind(0), Some(1));
+    assert!(cfg.is_unwind_edge(0, 1));
+
+    // the landing pad may be entered before `x` gets overwritten
+    let live = Liveness::new(&cfg, &arena);
+    assert_eq!(live.live_in(1), Some(&set(&["x"])));
+    assert_eq!(live.live_in(0), Some(&set(&["q", "x"])));
+    assert_eq!(live.live_out(0), Some(&set(&[])));
+
+    let mut after = Vec::new();
+    live.foreach_live_after(0, &arena.bbs()[&0], |n, _, live| {
+        after.push((n, live.clone()));
+    });
+    assert_eq!(after, [(1, set(&[])), (0, set(&["t", "x"]))]);
+}
@@ -0,0 +1,411 @@
+use core::marker::PhantomData;
+use yz_basic_block::{
+    analysis::Cfg,
+    jump::{Call, Dummy, Switch, Unconditional},
+    pass::{
+        CollapseSwitches, ForwardJumps, MergeChains, Pass, PassManager, PassStats,
+        RemoveUnreachable,
+    },
+    Arena, BasicBlock, BasicBlockInner, BbId, OptimizeReport,
+};
+
+type DummyArena = Arena<Dummy<BbId>, Dummy<BbId>>;
+
+/// builds a chain `0 -> 1 -> 2`, where BB 1 is empty
+fn chain() -> DummyArena {
+    let mut arena = DummyArena::new();
+    let bbs = [
+        (1, Unconditional::Jump(1)),
+        (0, Unconditional::Jump(2)),
+        (1, Unconditional::Return),
+    ];
+    for (n, (stmtcnt, next)) in bbs.iter().cloned().enumerate() {
+        arena.bbs_mut().insert(
+            n,
+            BasicBlock {
+                inner: BasicBlockInner::Concrete {
+                    statements: (0..stmtcnt).map(|_| Dummy(PhantomData)).collect(),
+                    condjmp: None,
+                    next,
+                    unwind: None,
+                },
+                is_public: n == 0,
+            },
+        );
+    }
+    arena.check().unwrap();
+    arena
+}
+
+#[test]
+fn standard_passes() {
+    let mut arena = chain();
+    let mut pm = PassManager::standard();
+    assert_eq!(
+        pm.names().collect::<Vec<_>>(),
+        [
+            "remove-unreachable",
+            "collapse-switches",
+            "forward-jumps",
+            "merge-chains"
+        ]
+    );
+    assert!(pm.run(&mut arena).is_modified());
+    assert_eq!(arena.len(), 1);
+    assert!(arena.check().is_ok());
+    if let BasicBlockInner::Concrete {
+        statements, next, ..
+    } = &arena.bbs()[&0].inner
+    {
+        assert_eq!(statements.len(), 2);
+        assert_eq!(*next, Unconditional::Return);
+    } else {
+        unreachable!();
+    }
+
+    let stats: Vec<_> = pm.stats().map(|(_, s)| *s).collect();
+    assert_eq!(pm.iterations(), 3);
+    assert_eq!(
+        stats[0],
+        PassStats {
+            runs: 3,
+            changes: 1
+        }
+    );
+    // there are no switches
+    assert_eq!(
+        stats[1],
+        PassStats {
+            runs: 3,
+            changes: 0
+        }
+    );
+    assert_eq!(
+        stats[2],
+        PassStats {
+            runs: 3,
+            changes: 1
+        }
+    );
+    assert_eq!(
+        stats[3],
+        PassStats {
+            runs: 3,
+            changes: 1
+        }
+    );
+
+    // nothing left to do
+    assert!(!pm.run(&mut arena).is_modified());
+    assert!(!arena.optimize().is_modified());
+}
+
+#[test]
+fn disable_merging() {
+    let mut arena = chain();
+    let mut pm = PassManager::standard();
+    assert!(pm.remove("merge-chains"));
+    assert!(!pm.remove("merge-chains"));
+    assert!(pm.run(&mut arena).is_modified());
+    assert_eq!(arena.bbs().keys().copied().collect::<Vec<_>>(), [0, 2]);
+    assert!(arena.check().is_ok());
+}
+
+#[test]
+fn single_passes() {
+    let mut arena = chain();
+    let mut report = OptimizeReport::default();
+    assert!(ForwardJumps.run(&mut arena, &mut report));
+    assert_eq!(arena.len(), 3);
+    assert!(arena.predecessors(1).unwrap().bbs.is_empty());
+    assert!(!MergeChains.run(&mut arena, &mut report));
+    assert!(RemoveUnreachable.run(&mut arena, &mut report));
+    assert_eq!(arena.len(), 2);
+    assert!(MergeChains.run(&mut arena, &mut report));
+    assert_eq!(arena.len(), 1);
+}
+
+/// custom pass which makes every BB non-public
+struct Unpublish;
+
+impl Pass<Dummy<BbId>, Dummy<BbId>> for Unpublish {
+    fn name(&self) -> &str {
+        "unpublish"
+    }
+
+    fn run(&mut self, arena: &mut DummyArena, _: &mut OptimizeReport) -> bool {
+        let mut modified = false;
+        for i in arena.bbs_mut().values_mut() {
+            modified |= i.is_public;
+            i.is_public = false;
+        }
+        modified
+    }
+}
+
+#[test]
+fn custom_pass() {
+    let mut arena = chain();
+    let mut pm = PassManager::new();
+    pm.add(Unpublish).add(RemoveUnreachable);
+    assert!(pm.run(&mut arena).is_modified());
+    assert!(arena.is_empty());
+    let stats: Vec<_> = pm.stats().collect();
+    assert_eq!(
+        stats[0],
+        (
+            "unpublish",
+            &PassStats {
+                runs: 2,
+                changes: 1
+            }
+        )
+    );
+}
+
+#[test]
+fn optimize_report() {
+    let mut arena = chain();
+    arena.set_label("tail".into(), 2, false).unwrap();
+    let report = arena.optimize();
+    assert!(report.is_modified());
+    assert_eq!(report.forwarded.iter().collect::<Vec<_>>(), [(&1, &2)]);
+    assert_eq!(report.removed.iter().collect::<Vec<_>>(), [&1]);
+    assert_eq!(report.merged.iter().collect::<Vec<_>>(), [(&2, &0)]);
+    assert_eq!(report.dropped_labels.iter().collect::<Vec<_>>(), ["tail"]);
+    assert!(arena.labels().is_empty());
+
+    assert_eq!(report.remap(0), Some(0));
+    assert_eq!(report.remap(1), Some(0));
+    assert_eq!(report.remap(2), Some(0));
+    assert_eq!(report.remap(3), Some(3));
+
+    let mut locs = std::collections::BTreeMap::new();
+    locs.insert(0, vec!["a.src:1"]);
+    locs.insert(2, vec!["a.src:3"]);
+    let locs = report.remap_map(locs, |a, mut b| a.append(&mut b));
+    assert_eq!(locs.len(), 1);
+    assert_eq!(locs[&0], ["a.src:1", "a.src:3"]);
+}
+
+#[test]
+fn optimize_report_unreachable() {
+    let mut arena = chain();
+    arena.set_label("mid".into(), 1, false).unwrap();
+    arena.bbs_mut().get_mut(&0).unwrap().is_public = false;
+    arena.check().unwrap();
+    let report = arena.optimize();
+    assert!(arena.is_empty());
+    assert_eq!(report.removed.len(), 3);
+    assert!(report.dropped_labels.contains("mid"));
+    assert_eq!(report.remap(1), None);
+}
+
+#[test]
+fn collapse_switches() {
+    let mut arena = DummyArena::new();
+    let switch = |cases: Vec<BbId>, default| Unconditional::Switch(Switch { cases, default });
+    let bbs = [
+        (1, switch(vec![1, 2], 2)),
+        (0, Unconditional::Jump(2)),
+        (1, switch(vec![3, 3], 3)),
+        (1, Unconditional::Return),
+    ];
+    for (n, (stmtcnt, next)) in bbs.iter().cloned().enumerate() {
+        arena.bbs_mut().insert(
+            n,
+            BasicBlock {
+                inner: BasicBlockInner::Concrete {
+                    statements: (0..stmtcnt).map(|_| Dummy(PhantomData)).collect(),
+                    condjmp: None,
+                    next,
+                    unwind: None,
+                },
+                is_public: n == 0,
+            },
+        );
+    }
+    arena.check().unwrap();
+    let cfg = Cfg::from_arena(&arena);
+    assert_eq!(cfg.succs(0), [1, 2]);
+    assert!(!cfg.exits().contains(&0));
+
+    let mut report = OptimizeReport::default();
+    assert!(CollapseSwitches.run(&mut arena, &mut report));
+    assert_eq!(report.collapsed.iter().collect::<Vec<_>>(), [&2]);
+    assert!(!CollapseSwitches.run(&mut arena, &mut report));
+
+    // after forwarding BB 1, the switch of BB 0 collapses too
+    let report = arena.optimize();
+    assert_eq!(report.collapsed.iter().collect::<Vec<_>>(), [&0]);
+    assert_eq!(report.forwarded.iter().collect::<Vec<_>>(), [(&1, &2)]);
+    assert_eq!(arena.len(), 1);
+    match &arena.bbs()[&0].inner {
+        BasicBlockInner::Concrete {
+            statements, next, ..
+        } => {
+            assert_eq!(statements.len(), 3);
+            assert_eq!(*next, Unconditional::Return);
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn indirect_jumps() {
+    let mut arena = DummyArena::new();
+    let bbs = [
+        (1, Unconditional::Indirect(Some(vec![1, 3]))),
+        (1, Unconditional::Return),
+        (1, Unconditional::Return),
+        (1, Unconditional::Indirect(None)),
+        (0, Unconditional::Jump(5)),
+        (1, Unconditional::Return),
+        (1, Unconditional::Return),
+    ];
+    for (n, (stmtcnt, next)) in bbs.iter().cloned().enumerate() {
+        arena.bbs_mut().insert(
+            n,
+            BasicBlock {
+                inner: BasicBlockInner::Concrete {
+                    statements: (0..stmtcnt).map(|_| Dummy(PhantomData)).collect(),
+                    condjmp: None,
+                    next,
+                    unwind: None,
+                },
+                is_public: n == 0,
+            },
+        );
+    }
+    arena.check().unwrap();
+    arena.set_label("addr".into(), 4, false).unwrap();
+    arena.set_label("tail".into(), 5, false).unwrap();
+
+    let cfg = Cfg::new(&arena, vec![0]);
+    assert_eq!(cfg.succs(0), [1, 3]);
+    assert_eq!(cfg.succs(3), [4, 5]);
+    assert!(cfg.exits().iter().all(|&i| i == 1 || i == 5));
+
+    // BB 2 and 6 are unreachable, the labeled BBs are kept alive by BB 3,
+    // BB 4 gets forwarded, but BB 5 can't be merged into it
+    let report = arena.optimize();
+    assert_eq!(
+        report.removed.iter().copied().collect::<Vec<_>>(),
+        [2, 4, 6]
+    );
+    assert!(report.merged.is_empty());
+    assert_eq!(arena.labels()["addr"], 5);
+    assert_eq!(arena.labels()["tail"], 5);
+
+    // without the indirect jump, the labels are dropped
+    arena.bbs_mut().get_mut(&3).unwrap().inner = BasicBlockInner::Concrete {
+        statements: vec![],
+        condjmp: None,
+        next: Unconditional::Halt,
+        unwind: None,
+    };
+    arena.check().unwrap();
+    arena.optimize();
+    assert_eq!(arena.bbs().keys().copied().collect::<Vec<_>>(), [0, 1, 3]);
+    assert!(arena.labels().is_empty());
+
+    // declared targets are validated
+    arena.bbs_mut().get_mut(&3).unwrap().inner = BasicBlockInner::Concrete {
+        statements: vec![],
+        condjmp: None,
+        next: Unconditional::Indirect(Some(vec![1, 7])),
+        unwind: None,
+    };
+    assert_eq!(arena.check().unwrap_err().0, [(3, 7)]);
+}
+
+#[test]
+fn calls() {
+    let mut arena = chain();
+    // 0 -> call 3 -> 1 -> 2; 3 -> 4, where BB 1 is empty
+    let callee = |next| BasicBlock {
+        inner: BasicBlockInner::Concrete {
+            statements: vec![Dummy(PhantomData)],
+            condjmp: None,
+            next,
+            unwind: None,
+        },
+        is_public: false,
+    };
+    arena.bbs_mut().insert(3, callee(Unconditional::Jump(4)));
+    arena.bbs_mut().insert(4, callee(Unconditional::Return));
+    if let BasicBlockInner::Concrete { next, .. } = &mut arena.bbs_mut().get_mut(&0).unwrap().inner
+    {
+        *next = Unconditional::Call(Call {
+            callee: 3,
+            continuation: 1,
+        });
+    }
+    arena.check().unwrap();
+
+    // the continuation is kept alive, but never merged into the call site,
+    // while the callee itself can be optimized
+    let report = arena.optimize();
+    assert_eq!(report.forwarded.iter().collect::<Vec<_>>(), [(&1, &2)]);
+    assert_eq!(report.merged.iter().collect::<Vec<_>>(), [(&4, &3)]);
+    assert_eq!(arena.bbs().keys().copied().collect::<Vec<_>>(), [0, 2, 3]);
+    assert_eq!(
+        arena
+            .predecessors(2)
+            .unwrap()
+            .bbs
+            .iter()
+            .collect::<Vec<_>>(),
+        [&0]
+    );
+    assert!(!arena.optimize().is_modified());
+}
+
+#[test]
+fn unwind_targets() {
+    let mut arena = DummyArena::new();
+    let bbs = [
+        (Some(3), Unconditional::Jump(1)),
+        (Some(3), Unconditional::Jump(2)),
+        (None, Unconditional::Return),
+        (None, Unconditional::Return),
+    ];
+    for (n, (unwind, next)) in bbs.iter().cloned().enumerate() {
+        arena.bbs_mut().insert(
+            n,
+            BasicBlock {
+                inner: BasicBlockInner::Concrete {
+                    statements: vec![Dummy(PhantomData)],
+                    condjmp: None,
+                    next,
+                    unwind,
+                },
+                is_public: n == 0,
+            },
+        );
+    }
+    arena.check().unwrap();
+    assert_eq!(Cfg::from_arena(&arena).succs(0), [3, 1]);
+
+    // the landing pad is kept alive, and BB 2 unwinds differently than BB 0
+    let report = arena.optimize();
+    assert!(report.removed.is_empty());
+    assert_eq!(report.merged.iter().collect::<Vec<_>>(), [(&1, &0)]);
+    assert_eq!(arena.bbs().keys().copied().collect::<Vec<_>>(), [0, 2, 3]);
+    match &arena.bbs()[&0].inner {
+        BasicBlockInner::Concrete {
+            statements,
+            next,
+            unwind,
+            ..
+        } => {
+            assert_eq!(statements.len(), 2);
+            assert_eq!(*next, Unconditional::Jump(2));
+            assert_eq!(*unwind, Some(3));
+        }
+        _ => unreachable!(),
+    }
+
+    // unwind targets are validated
+    arena.remove(3).unwrap().unwrap_err();
+}
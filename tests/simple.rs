@@ -1,7 +1,7 @@
 #[allow(unused_imports)]
 use yz_basic_block::{
-    jump::{Dummy, Unconditional},
-    Arena, BasicBlock, BasicBlockInner,
+    jump::{Dummy, Switch, Unconditional},
+    Arena, BasicBlock, BasicBlockInner, BranchWeights, FillError, OffendingIds,
 };
 
 type DummyArena = Arena<Dummy<usize>, Dummy<usize>>;
@@ -23,6 +23,7 @@ fn bb1() {
             statements: Vec::new(),
             condjmp: None,
             next: Unconditional::Halt,
+            unwind: None,
         },
         is_public: true,
     });
@@ -35,6 +36,7 @@ fn bb1() {
             statements: Vec::new(),
             condjmp: None,
             next: Unconditional::Jump(5),
+            unwind: None,
         },
         is_public: true,
     });
@@ -58,6 +60,7 @@ fn bb_pop() {
             statements: Vec::new(),
             condjmp: None,
             next: Unconditional::Halt,
+            unwind: None,
         },
         is_public: true,
     });
@@ -71,7 +74,7 @@ fn bb_pop() {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct LolCondJmp<T> {
     target: T,
 }
@@ -103,6 +106,7 @@ fn bb_keep() {
             statements: Vec::new(),
             condjmp: None,
             next: Unconditional::Halt,
+            unwind: None,
         },
         is_public: true,
     });
@@ -114,6 +118,7 @@ fn bb_keep() {
             statements: Vec::new(),
             condjmp: None,
             next: Unconditional::Halt,
+            unwind: None,
         },
         is_public: false,
     });
@@ -147,6 +152,7 @@ fn bb_merge() {
             statements: Vec::new(),
             condjmp: None,
             next: Unconditional::Halt,
+            unwind: None,
         },
         is_public: true,
     });
@@ -158,6 +164,7 @@ fn bb_merge() {
             statements: Vec::new(),
             condjmp: None,
             next: Unconditional::Return,
+            unwind: None,
         },
         is_public: false,
     });
@@ -184,3 +191,290 @@ fn bb_merge() {
         unreachable!();
     }
 }
+
+#[test]
+fn bb_preds() {
+    let mut arena = DummyArena::new();
+
+    let pr = arena.push(BasicBlock {
+        inner: BasicBlockInner::Concrete {
+            statements: Vec::new(),
+            condjmp: None,
+            next: Unconditional::Halt,
+            unwind: None,
+        },
+        is_public: false,
+    });
+    let tail = pr.unwrap();
+    let pr = arena.push(BasicBlock {
+        inner: BasicBlockInner::Concrete {
+            statements: Vec::new(),
+            condjmp: None,
+            next: Unconditional::Jump(tail),
+            unwind: None,
+        },
+        is_public: true,
+    });
+    let head = pr.unwrap();
+    assert!(arena.set_label("main".into(), head, false).is_ok());
+
+    let p = arena.predecessors(tail).unwrap();
+    assert_eq!(p.bbs.iter().copied().collect::<Vec<_>>(), [head]);
+    assert!(p.labels.is_empty());
+    let p = arena.predecessors(head).unwrap();
+    assert!(p.bbs.is_empty());
+    assert!(p.labels.contains("main"));
+
+    // move the label
+    assert_eq!(
+        arena.set_label("main".into(), tail, true).unwrap(),
+        Some(head)
+    );
+    assert!(arena.predecessors(head).unwrap().labels.is_empty());
+    assert!(arena.predecessors(tail).unwrap().labels.contains("main"));
+
+    // raw modifications invalidate the index until it gets rebuilt
+    arena.bbs_mut();
+    assert!(arena.predecessors(tail).is_none());
+    arena.check().unwrap();
+    assert!(arena.predecessors(tail).is_none());
+    arena.update_predecessors();
+    assert!(arena.predecessors(tail).unwrap().bbs.contains(&head));
+
+    assert_eq!(arena.remove(tail).unwrap().unwrap_err().0, &[(head, tail)]);
+    let (_, labels) = arena.remove(head).unwrap().unwrap();
+    assert!(labels.is_empty());
+    assert!(arena.predecessors(head).is_none());
+    assert!(arena.predecessors(tail).unwrap().bbs.is_empty());
+
+    let (_, labels) = arena.remove(tail).unwrap().unwrap();
+    assert_eq!(labels, ["main"]);
+    assert!(arena.is_empty());
+    assert!(arena.labels().is_empty());
+}
+
+#[test]
+fn bb_reserve_fill() {
+    let mut arena = Arena::<Dummy<usize>, LolCondJmp<usize>>::new();
+    let a = arena.reserve();
+    let b = arena.reserve();
+    assert_eq!((a, b), (0, 1));
+    // unfilled reservations are reported
+    assert!(arena.check().is_err());
+
+    let bb = |condjmp, next| BasicBlock {
+        inner: BasicBlockInner::Concrete {
+            statements: Vec::new(),
+            condjmp: Some(LolCondJmp { target: condjmp }),
+            next,
+            unwind: None,
+        },
+        is_public: true,
+    };
+    assert!(arena.fill(a, bb(b, Unconditional::Halt)).is_ok());
+    let (_, e) = arena.fill(b, bb(5, Unconditional::Jump(a))).unwrap_err();
+    assert_eq!(e, FillError::Offending(OffendingIds(vec![(b, 5)])));
+    assert!(arena.fill(b, bb(a, Unconditional::Jump(b))).is_ok());
+    let (_, e) = arena.fill(a, bb(b, Unconditional::Return)).unwrap_err();
+    assert_eq!(e, FillError::NotPlaceholder(a));
+    let (_, e) = arena.fill(7, bb(b, Unconditional::Return)).unwrap_err();
+    assert_eq!(e, FillError::InvalidId(7));
+
+    assert_eq!(
+        arena
+            .predecessors(a)
+            .unwrap()
+            .bbs
+            .iter()
+            .collect::<Vec<_>>(),
+        [&b]
+    );
+    assert_eq!(
+        arena
+            .predecessors(b)
+            .unwrap()
+            .bbs
+            .iter()
+            .collect::<Vec<_>>(),
+        [&a, &b]
+    );
+    arena.check().unwrap();
+
+    // IDs stay dense
+    let c = arena.reserve();
+    assert_eq!(c, 2);
+    arena.remove(c).unwrap().unwrap();
+    assert_eq!(arena.reserve(), c);
+}
+
+#[test]
+fn bb_generations() {
+    let mut arena = DummyArena::new();
+    let bb = |is_public| BasicBlock {
+        inner: BasicBlockInner::Concrete {
+            statements: Vec::new(),
+            condjmp: None,
+            next: Unconditional::Halt,
+            unwind: None,
+        },
+        is_public,
+    };
+    let a = arena.push(bb(true)).unwrap();
+    let ga = arena.gen_id(a).unwrap();
+    assert_eq!(ga.generation(), 0);
+    assert!(arena.get(ga).is_some());
+    assert!(arena.gen_id(a + 1).is_none());
+
+    // the slot gets reused, but the old ID is stale
+    arena.remove(a).unwrap().unwrap();
+    assert!(arena.get(ga).is_none());
+    assert_eq!(arena.push(bb(false)).unwrap(), a);
+    assert!(arena.get(ga).is_none());
+    let ga2 = arena.gen_id(a).unwrap();
+    assert_eq!((ga2.id(), ga2.generation()), (a, 1));
+    assert!(!arena.get(ga2).unwrap().is_public);
+
+    // optimize removes it
+    arena.optimize();
+    assert!(arena.get(ga2).is_none());
+    assert_eq!(arena.generation(a), 2);
+
+    // so does rolling back its insertion
+    let mut tr = arena.transaction();
+    let b = tr.push(bb(true)).unwrap();
+    let gb = tr.gen_id(b).unwrap();
+    tr.rollback();
+    assert!(arena.get(gb).is_none());
+
+    // rolling back a removal restores the BB together with its generation,
+    // even if the slot got reused in the meantime
+    let c = arena.push(bb(true)).unwrap();
+    let gc = arena.gen_id(c).unwrap();
+    let mut tr = arena.transaction();
+    tr.remove(c).unwrap().unwrap();
+    assert_eq!(tr.push(bb(false)).unwrap(), c);
+    let gc2 = tr.gen_id(c).unwrap();
+    assert_ne!(gc2, gc);
+    tr.rollback();
+    assert!(arena.get(gc).unwrap().is_public);
+    assert!(arena.get(gc2).is_none());
+}
+
+#[test]
+fn bb_labels() {
+    let mut arena = DummyArena::new();
+    let a = arena
+        .push(BasicBlock {
+            inner: BasicBlockInner::Concrete {
+                statements: Vec::new(),
+                condjmp: None,
+                next: Unconditional::Halt,
+                unwind: None,
+            },
+            is_public: true,
+        })
+        .unwrap();
+    let b = arena
+        .push(BasicBlock {
+            inner: BasicBlockInner::Placeholder { is_extern: true },
+            is_public: false,
+        })
+        .unwrap();
+    for (label, target) in [("main", a), ("start", a), ("puts", b)] {
+        arena.set_label(label.into(), target, false).unwrap();
+    }
+    assert_eq!(arena.labels_of_bb(a).collect::<Vec<_>>(), ["main", "start"]);
+
+    // rename
+    assert!(arena.rename_label("nope", "x".into(), false).is_none());
+    assert!(arena
+        .rename_label("start", "puts".into(), false)
+        .unwrap()
+        .is_err());
+    assert_eq!(
+        arena.rename_label("start", "_start".into(), false).unwrap(),
+        Ok(None)
+    );
+    assert_eq!(
+        arena.labels_of_bb(a).collect::<Vec<_>>(),
+        ["_start", "main"]
+    );
+    assert!(arena.predecessors(a).unwrap().labels.contains("_start"));
+
+    // remove
+    assert_eq!(arena.remove_label("_start"), Some(a));
+    assert_eq!(arena.remove_label("_start"), None);
+    assert_eq!(arena.labels_of_bb(a).collect::<Vec<_>>(), ["main"]);
+
+    // works without a valid index too
+    arena.bbs_mut();
+    assert_eq!(arena.labels_of_bb(b).collect::<Vec<_>>(), ["puts"]);
+    arena.check().unwrap();
+
+    // transactions record label changes
+    let mut tr = arena.transaction();
+    assert_eq!(
+        tr.rename_label("main", "puts".into(), true).unwrap(),
+        Ok(Some(b))
+    );
+    assert_eq!(tr.remove_label("puts"), Some(a));
+    tr.rollback();
+    assert_eq!(arena.labels_of_bb(a).collect::<Vec<_>>(), ["main"]);
+    assert_eq!(arena.labels_of_bb(b).collect::<Vec<_>>(), ["puts"]);
+
+    // placeholders need labels
+    arena.remove_label("puts");
+    assert_eq!(arena.check().unwrap_err().0, [(b, b)]);
+}
+
+#[test]
+fn bb_branch_weights() {
+    let mut arena = Arena::<Dummy<usize>, LolCondJmp<usize>>::new();
+    let bb = |condjmp, next| BasicBlock {
+        inner: BasicBlockInner::Concrete {
+            statements: Vec::new(),
+            condjmp: Some(LolCondJmp { target: condjmp }),
+            next,
+            unwind: None,
+        },
+        is_public: true,
+    };
+    let a = arena.reserve();
+    let c = arena.push(bb(a, Unconditional::Return)).unwrap();
+    let b = arena
+        .push(bb(
+            c,
+            Unconditional::Switch(Switch {
+                cases: vec![c],
+                default: c,
+            }),
+        ))
+        .unwrap();
+    arena.fill(a, bb(c, Unconditional::Jump(b))).unwrap();
+
+    let condjmp = BranchWeights {
+        condjmp: Some((1, 3)),
+        switch: None,
+    };
+    let switch = |w: Vec<u32>| BranchWeights {
+        condjmp: None,
+        switch: Some(w),
+    };
+    assert_eq!(arena.set_branch_weights(a, condjmp.clone()), Ok(None));
+    assert!(arena.set_branch_weights(a, switch(vec![1, 2])).is_err());
+    assert!(arena.set_branch_weights(b, switch(vec![1])).is_err());
+    assert!(arena.set_branch_weights(7, condjmp.clone()).is_err());
+    assert_eq!(arena.set_branch_weights(b, switch(vec![1, 2])), Ok(None));
+
+    // rolled back together with the transaction
+    let mut tx = arena.transaction();
+    assert_eq!(tx.remove_branch_weights(a), Some(condjmp.clone()));
+    assert!(tx.branch_weights(a).is_none());
+    drop(tx);
+    assert_eq!(arena.branch_weights(a), Some(&condjmp));
+
+    // the collapsed switch takes its weights with it
+    assert!(arena.optimize().collapsed.contains(&b));
+    assert!(arena.branch_weights(b).is_none());
+    assert_eq!(arena.branch_weights(a), Some(&condjmp));
+}
@@ -0,0 +1,306 @@
+use yz_basic_block::{jump::Unconditional, ssa::Ssa, BasicBlockInner};
+
+mod common;
+use common::vars::{assign, build, fresh_gen, set_unwind, statements, Assign, Branch};
+
+#[test]
+fn ssa_diamond() {
+    // 0: c = ; if c goto 2; goto 1
+    // 1: x = ; goto 3
+    // 2: x = ; goto 3
+    // 3: r = x; return
+    let arena = build(vec![
+        (
+            vec![assign("c", &[])],
+            Some(("c", 2)),
+            Unconditional::Jump(1),
+        ),
+        (vec![assign("x", &[])], None, Unconditional::Jump(3)),
+        (vec![assign("x", &[])], None, Unconditional::Jump(3)),
+        (vec![assign("r", &["x"])], None, Unconditional::Return),
+    ]);
+    let ssa = Ssa::construct(arena, fresh_gen());
+    assert_eq!(ssa.params.len(), 1);
+    let p = ssa.params_of(3).to_vec();
+    assert_eq!(p.len(), 1);
+    assert_eq!(statements(&ssa.arena, 3)[0].srcs, p);
+    let x1 = statements(&ssa.arena, 1)[0].dst.clone();
+    let x2 = statements(&ssa.arena, 2)[0].dst.clone();
+    assert_ne!(x1, x2);
+    assert_eq!(ssa.args_of(1, 3), &[x1.as_str()]);
+    assert_eq!(ssa.args_of(2, 3), &[x2.as_str()]);
+    assert!(ssa.args_of(0, 1).is_empty());
+
+    // every variable is defined exactly once
+    let mut defs: Vec<_> = ssa
+        .arena
+        .bbs()
+        .keys()
+        .flat_map(|&i| statements(&ssa.arena, i).iter().map(|s| s.dst.clone()))
+        .chain(p.iter().cloned())
+        .collect();
+    let cnt = defs.len();
+    defs.sort();
+    defs.dedup();
+    assert_eq!(defs.len(), cnt);
+
+    let arena = ssa.destruct(fresh_gen());
+    assert_eq!(arena.len(), 4);
+    assert_eq!(statements(&arena, 1)[1], assign(&p[0], &[&x1]));
+    assert_eq!(statements(&arena, 2)[1], assign(&p[0], &[&x2]));
+}
+
+#[test]
+fn ssa_loop_with_swap() {
+    // 0: a = ; b = ; goto 1
+    // 1: c = a b; if c goto 1; goto 2      (swaps a and b in each iteration)
+    //    t = a; a = b; b = t
+    // 2: r = a b; return
+    let arena = build(vec![
+        (
+            vec![assign("a", &[]), assign("b", &[])],
+            None,
+            Unconditional::Jump(1),
+        ),
+        (
+            vec![
+                assign("c", &["a", "b"]),
+                assign("t", &["a"]),
+                assign("a", &["b"]),
+                assign("b", &["t"]),
+            ],
+            Some(("c", 1)),
+            Unconditional::Jump(2),
+        ),
+        (vec![assign("r", &["a", "b"])], None, Unconditional::Return),
+    ]);
+    let ssa = Ssa::construct(arena, fresh_gen());
+    let p = ssa.params_of(1).to_vec();
+    assert_eq!(p.len(), 2);
+    assert!(ssa.params_of(2).is_empty());
+    assert_eq!(statements(&ssa.arena, 1)[0].srcs, p);
+    assert_eq!(ssa.args_of(0, 1).len(), 2);
+    assert_eq!(ssa.args_of(1, 1).len(), 2);
+
+    // the back edge is critical and has to be split
+    let arena = ssa.destruct(fresh_gen());
+    assert_eq!(arena.len(), 4);
+    assert!(arena.check().is_ok());
+    let split = match &arena.bbs()[&1].inner {
+        BasicBlockInner::Concrete { condjmp, .. } => condjmp.as_ref().unwrap().target,
+        _ => unreachable!(),
+    };
+    assert_eq!(split, 3);
+    match &arena.bbs()[&3].inner {
+        BasicBlockInner::Concrete {
+            statements, next, ..
+        } => {
+            assert_eq!(statements.len(), 2);
+            assert_eq!(*next, Unconditional::Jump(1));
+        }
+        _ => unreachable!(),
+    }
+    // the entry edge is not critical, the copies get appended
+    assert_eq!(statements(&arena, 0).len(), 4);
+}
+
+#[test]
+fn ssa_public_loop_header() {
+    // 0: y = x; if c goto 1; return      (public, entered with x from outside)
+    // 1: x = x; goto 0
+    let arena = build(vec![
+        (
+            vec![assign("y", &["x"])],
+            Some(("c", 1)),
+            Unconditional::Return,
+        ),
+        (vec![assign("x", &["x"])], None, Unconditional::Jump(0)),
+    ]);
+    let ssa = Ssa::construct(arena, fresh_gen());
+    // the parameter keeps the name of the value passed from outside
+    assert_eq!(ssa.params_of(0), ["x"]);
+    assert_eq!(statements(&ssa.arena, 0)[0].srcs, ["x"]);
+    assert_eq!(statements(&ssa.arena, 1)[0].srcs, ["x"]);
+    let x1 = statements(&ssa.arena, 1)[0].dst.clone();
+    assert_ne!(x1, "x");
+    assert_eq!(ssa.args_of(1, 0), [x1.as_str()]);
+
+    let arena = ssa.destruct(fresh_gen());
+    assert_eq!(arena.len(), 2);
+    assert_eq!(statements(&arena, 0)[0].srcs, ["x"]);
+    assert_eq!(statements(&arena, 1)[1], assign("x", &[&x1]));
+}
+
+#[test]
+fn ssa_cyclic_copies() {
+    // 0: a = ; b = ; goto 1
+    // 1: goto 2
+    // 2: t = a; a = b; b = t; goto 1
+    let arena = build(vec![
+        (
+            vec![assign("a", &[]), assign("b", &[])],
+            None,
+            Unconditional::Jump(1),
+        ),
+        (vec![], None, Unconditional::Jump(2)),
+        (
+            vec![
+                assign("t", &["a"]),
+                assign("a", &["b"]),
+                assign("b", &["t"]),
+            ],
+            None,
+            Unconditional::Jump(1),
+        ),
+    ]);
+    let mut ssa = Ssa::construct(arena, fresh_gen());
+    // make the loop body a plain back edge which passes a swapped pair
+    let p = ssa.params_of(1).to_vec();
+    assert_eq!(p.len(), 2);
+    ssa.args.insert((2, 1), vec![p[1].clone(), p[0].clone()]);
+    if let BasicBlockInner::Concrete { statements, .. } =
+        &mut ssa.arena.bbs_mut().get_mut(&2).unwrap().inner
+    {
+        statements.clear();
+    }
+
+    let arena = ssa.destruct(fresh_gen());
+    let copies = statements(&arena, 2);
+    assert_eq!(copies.len(), 3);
+    // a temporary is used to break the cycle
+    assert_eq!(copies[0].srcs, [p[0].clone()]);
+    assert_eq!(copies[1], assign(&p[0], &[&p[1]]));
+    assert_eq!(copies[2], assign(&p[1], &[&copies[0].dst]));
+}
+
+fn implicit_edges() -> Ssa<Assign, Branch, String> {
+    // 0: x = x; ijmp *      (public)
+    // 1: r = x; return      (label "a")
+    // 2: s = x; return      (label "b")
+    let mut arena = build(vec![
+        (
+            vec![assign("x", &["x"])],
+            None,
+            Unconditional::Indirect(None),
+        ),
+        (vec![assign("r", &["x"])], None, Unconditional::Return),
+        (vec![assign("s", &["x"])], None, Unconditional::Return),
+    ]);
+    arena.set_label("a".into(), 1, false).unwrap();
+    arena.set_label("b".into(), 2, false).unwrap();
+    Ssa::construct(arena, fresh_gen())
+}
+
+#[test]
+fn ssa_implicit_edges() {
+    let ssa = implicit_edges();
+    assert_eq!(ssa.params_of(1), ["x"]);
+    assert_eq!(ssa.params_of(2), ["x"]);
+    let x1 = statements(&ssa.arena, 0)[0].dst.clone();
+    assert_eq!(ssa.args_of(0, 1), [x1.as_str()]);
+    assert_eq!(ssa.args_of(0, 2), [x1.as_str()]);
+
+    // both edges share a single copy, the jump can't be split
+    let arena = ssa.destruct(fresh_gen());
+    assert_eq!(arena.len(), 3);
+    assert_eq!(
+        statements(&arena, 0),
+        [assign(&x1, &["x"]), assign("x", &[&x1])]
+    );
+}
+
+#[test]
+#[should_panic(expected = "conflicting copies")]
+fn ssa_implicit_edges_conflict() {
+    let mut ssa = implicit_edges();
+    ssa.args.insert((0, 2), vec!["y".to_string()]);
+    ssa.destruct(fresh_gen());
+}
+
+#[test]
+fn ssa_unwind() {
+    // 0: c = ; if c goto 2; goto 1
+    // 1: x = ; goto 3
+    // 2: x = ; goto 4
+    // 3: s = ; return; unwind 5
+    // 4: t = ; return; unwind 5
+    // 5: r = x; return
+    let mut arena = build(vec![
+        (
+            vec![assign("c", &[])],
+            Some(("c", 2)),
+            Unconditional::Jump(1),
+        ),
+        (vec![assign("x", &[])], None, Unconditional::Jump(3)),
+        (vec![assign("x", &[])], None, Unconditional::Jump(4)),
+        (vec![assign("s", &[])], None, Unconditional::Return),
+        (vec![assign("t", &[])], None, Unconditional::Return),
+        (vec![assign("r", &["x"])], None, Unconditional::Return),
+    ]);
+    set_unwind(&mut arena, 3, 5);
+    set_unwind(&mut arena, 4, 5);
+    let ssa = Ssa::construct(arena, fresh_gen());
+    let p = ssa.params_of(5).to_vec();
+    assert_eq!(p.len(), 1);
+    assert_eq!(statements(&ssa.arena, 5)[0].srcs, p);
+    let x1 = statements(&ssa.arena, 1)[0].dst.clone();
+    assert_eq!(ssa.args_of(3, 5), [x1.as_str()]);
+
+    // the copies can't be appended to the unwinding BBs
+    let arena = ssa.destruct(fresh_gen());
+    assert_eq!(arena.len(), 8);
+    assert_eq!(statements(&arena, 3).len(), 1);
+    assert_eq!(statements(&arena, 4).len(), 1);
+    let split = match &arena.bbs()[&3].inner {
+        BasicBlockInner::Concrete { unwind, .. } => unwind.unwrap(),
+        _ => unreachable!(),
+    };
+    assert_eq!(statements(&arena, split), [assign(&p[0], &[&x1])]);
+}
+
+#[test]
+fn ssa_unwind_redefinition() {
+    // 0: t = q; x = t; y = x; return; unwind 1
+    // 1: r = x; return
+    let mut arena = build(vec![
+        (
+            vec![
+                assign("t", &["q"]),
+                assign("x", &["t"]),
+                assign("y", &["x"]),
+            ],
+            None,
+            Unconditional::Return,
+        ),
+        (vec![assign("r", &["x"])], None, Unconditional::Return),
+    ]);
+    set_unwind(&mut arena, 0, 1);
+    let ssa = Ssa::construct(arena, fresh_gen());
+
+    // bb0 got split after the redefinition of x, the landing pad
+    // sees the old x from the head and the new one from the tail
+    let unwind_of = |arena: &common::vars::TestArena, bbid| match &arena.bbs()[&bbid].inner {
+        BasicBlockInner::Concrete { next, unwind, .. } => (next.clone(), *unwind),
+        _ => unreachable!(),
+    };
+    assert_eq!(ssa.arena.len(), 3);
+    assert_eq!(unwind_of(&ssa.arena, 0), (Unconditional::Jump(2), Some(1)));
+    assert_eq!(unwind_of(&ssa.arena, 2), (Unconditional::Return, Some(1)));
+    assert_eq!(statements(&ssa.arena, 0).len(), 2);
+    let x1 = statements(&ssa.arena, 0)[1].dst.clone();
+    assert_eq!(statements(&ssa.arena, 2)[0].srcs, [x1.as_str()]);
+
+    let p = ssa.params_of(1).to_vec();
+    assert_eq!(p.len(), 1);
+    assert_ne!(p[0], x1);
+    assert_eq!(statements(&ssa.arena, 1)[0].srcs, p);
+    assert_eq!(ssa.args_of(0, 1), ["x"]);
+    assert_eq!(ssa.args_of(2, 1), [x1.as_str()]);
+
+    // every unwind edge gets its own copies
+    let arena = ssa.destruct(fresh_gen());
+    let (_, pad0) = unwind_of(&arena, 0);
+    let (_, pad2) = unwind_of(&arena, 2);
+    assert_eq!(statements(&arena, pad0.unwrap()), [assign(&p[0], &["x"])]);
+    assert_eq!(statements(&arena, pad2.unwrap()), [assign(&p[0], &[&x1])]);
+}
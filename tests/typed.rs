@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use yz_basic_block::{
+    id::{Idx, TypedBbId},
+    jump::Unconditional,
+    BasicBlock, BasicBlockInner, BbId, CompactOrder, FillError, OffendingIds,
+};
+
+mod common;
+use common::TestBb;
+
+struct Main;
+type Id = TypedBbId<Main>;
+
+type TestArena = common::TestArena<Id>;
+
+fn bb(condjmp: Option<Id>, next: Unconditional<Id>) -> TestBb<Id> {
+    common::bb(true, condjmp, next)
+}
+
+#[test]
+fn typed_ids() {
+    let mut arena = TestArena::new();
+    let a = arena.reserve();
+    let b = arena.push(bb(Some(a), Unconditional::Return)).unwrap();
+    assert_eq!((a.get(), b.index()), (0, 1));
+    assert_eq!(format!("{:?}", b), "bb1");
+
+    let bogus = Id::from(5);
+    let (_, e) = arena
+        .fill(a, bb(None, Unconditional::Jump(bogus)))
+        .unwrap_err();
+    assert_eq!(e, FillError::Offending(OffendingIds(vec![(a, bogus)])));
+    arena.fill(a, bb(None, Unconditional::Jump(b))).unwrap();
+    arena.set_label("main".into(), a, false).unwrap();
+    assert_eq!(arena.label2bb("main").unwrap().0, a);
+    assert!(arena.predecessors(b).unwrap().bbs.contains(&a));
+    arena.check().unwrap();
+
+    {
+        let mut tr = arena.transaction();
+        tr.edit(|ed| {
+            ed.get_mut(b).unwrap().is_public = false;
+        })
+        .unwrap();
+        assert_eq!(tr.remove(b).unwrap().unwrap_err().0, [(a, b)]);
+    }
+    assert!(arena.bbs()[&b].is_public);
+
+    let mut bbm = arena.bb_mut(b).unwrap();
+    bbm.inner = BasicBlockInner::Placeholder { is_extern: false };
+    assert_eq!(bbm.commit(), Err(OffendingIds(vec![(b, b)])));
+
+    assert_eq!(BbId::from(b), 1);
+}
+
+#[test]
+fn typed_link_optimize_compact() {
+    let mut main = TestArena::new();
+    let p = main
+        .push(BasicBlock {
+            inner: BasicBlockInner::Placeholder { is_extern: false },
+            is_public: false,
+        })
+        .unwrap();
+    let a = main.push(bb(None, Unconditional::Jump(p))).unwrap();
+    main.set_label("f".into(), p, false).unwrap();
+    main.set_label("main".into(), a, false).unwrap();
+
+    let mut lib = TestArena::new();
+    let f = lib.push(bb(None, Unconditional::Return)).unwrap();
+    lib.set_label("f".into(), f, false).unwrap();
+
+    let maps = main.link(vec![lib]).map_err(|(_, e)| e).unwrap();
+    let f = maps[1][&f];
+    assert_eq!((maps[0][&p], f), (Id::from(2), Id::from(2)));
+    assert_eq!(main.label2bb("f").unwrap().0, f);
+
+    let u = main
+        .push(common::bb(false, None, Unconditional::Return))
+        .unwrap();
+    let report = main.optimize();
+    assert_eq!(report.removed.iter().collect::<Vec<_>>(), [&u]);
+    assert_eq!((report.remap(u), report.remap(f)), (None, Some(f)));
+
+    let map = main.compact(CompactOrder::ReversePostorder);
+    let expected: BTreeMap<_, _> = [(a, Id::from(0)), (f, Id::from(1))].into();
+    assert_eq!(map, expected);
+    assert_eq!(main.label2bb("f").unwrap().0, Id::from(1));
+    main.check().unwrap();
+}
@@ -0,0 +1,125 @@
+use yz_basic_block::{jump::Unconditional, BbId};
+
+mod common;
+use common::{TestArena, TestBb};
+
+fn bb(is_public: bool, next: Unconditional<BbId>) -> TestBb {
+    common::bb(is_public, None, next)
+}
+
+fn build() -> TestArena {
+    let mut arena = TestArena::new();
+    let a = arena.push(bb(false, Unconditional::Return)).unwrap();
+    let b = arena.push(bb(true, Unconditional::Jump(a))).unwrap();
+    arena.set_label("main".into(), b, false).unwrap();
+    arena
+}
+
+fn assert_same(x: &TestArena, y: &TestArena) {
+    assert_eq!(x.bbs(), y.bbs());
+    assert_eq!(x.labels(), y.labels());
+    for i in x.bbs().keys() {
+        assert_eq!(x.predecessors(*i), y.predecessors(*i));
+    }
+}
+
+#[test]
+fn rollback_on_drop() {
+    let orig = build();
+    let mut arena = build();
+    {
+        let mut tr = arena.transaction();
+        let c = tr.push(bb(true, Unconditional::Jump(1))).unwrap();
+        tr.set_label("main".into(), c, true).unwrap();
+        tr.set_label("other".into(), 1, false).unwrap();
+        let (_, labels) = tr.remove(c).unwrap().unwrap();
+        assert_eq!(labels, ["main"]);
+        tr.edit(|ed| {
+            ed.get_mut(1).unwrap().is_public = false;
+        })
+        .unwrap();
+        // failing batch edit
+        assert!(tr
+            .edit(|ed| {
+                *ed.get_mut(0).unwrap() = bb(false, Unconditional::Jump(9));
+            })
+            .is_err());
+        assert_eq!(tr.optimize().removed.len(), 2);
+        assert!(tr.is_empty());
+    }
+    assert_same(&arena, &orig);
+}
+
+#[test]
+fn checkpoints() {
+    let orig = build();
+    let mut arena = build();
+    let mut tr = arena.transaction();
+    let cp1 = tr.checkpoint();
+    let c = tr.push(bb(false, Unconditional::Halt)).unwrap();
+    let cp2 = tr.checkpoint();
+    tr.push(bb(false, Unconditional::Jump(c))).unwrap();
+    tr.set_label("x".into(), c, false).unwrap();
+    assert_eq!(tr.len(), 4);
+
+    tr.rollback_to(cp2);
+    assert_eq!(tr.len(), 3);
+    assert!(tr.labels().get("x").is_none());
+    assert!(tr.predecessors(c).unwrap().bbs.is_empty());
+    // the freed ID gets reused
+    assert_eq!(tr.reserve(), c + 1);
+
+    tr.rollback_to(cp1);
+    assert_eq!(tr.len(), 2);
+    tr.rollback();
+    assert_same(&arena, &orig);
+}
+
+#[test]
+fn undo_redo() {
+    let orig = build();
+    let mut arena = build();
+    let mut tr = arena.transaction();
+    let c = tr.reserve();
+    tr.fill(c, bb(true, Unconditional::Jump(0))).unwrap();
+    tr.set_label("main".into(), c, true).unwrap();
+    let log = tr.commit();
+    arena.check().unwrap();
+    let modified = {
+        let mut x = build();
+        let c = x.push(bb(true, Unconditional::Jump(0))).unwrap();
+        x.set_label("main".into(), c, true).unwrap();
+        x
+    };
+    assert_same(&arena, &modified);
+
+    let redo = log.undo(&mut arena);
+    assert_same(&arena, &orig);
+    let undo = redo.undo(&mut arena);
+    assert_same(&arena, &modified);
+    assert!(!undo.undo(&mut arena).is_empty());
+    assert_same(&arena, &orig);
+}
+
+#[test]
+fn snapshot_generations() {
+    let mut arena = build();
+    let (ga, gb) = (arena.gen_id(0).unwrap(), arena.gen_id(1).unwrap());
+    let (gc, gb2) = {
+        let mut tr = arena.transaction();
+        tr.with_arena(|x| {
+            let c = x.push(bb(false, Unconditional::Halt)).unwrap();
+            x.remove(1).unwrap().unwrap();
+            let b = x.push(bb(true, Unconditional::Jump(0))).unwrap();
+            assert_eq!(b, 1);
+            (x.gen_id(c).unwrap(), x.gen_id(b).unwrap())
+        })
+    };
+    assert_same(&arena, &build());
+    // untouched BBs stay valid
+    assert!(arena.get(ga).is_some());
+    // BBs which disappear or whose slot got reused don't
+    assert!(arena.get(gc).is_none());
+    assert!(arena.get(gb).is_none());
+    assert!(arena.get(gb2).is_none());
+}